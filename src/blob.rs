@@ -0,0 +1,188 @@
+use std::{fmt::Display, ops::Range};
+
+/// Bytes shown per line of a hex dump.
+pub const DUMP_WIDTH: usize = 16;
+
+/// What a blob appears to hold.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content {
+    Text(String),
+    Png,
+    Jpeg,
+    /// A libSQL `F32_BLOB` vector, little-endian floats.
+    Vector(Vec<f32>),
+    Binary,
+}
+
+impl Content {
+    pub fn is_decodable(&self) -> bool {
+        matches!(self, Content::Text(_) | Content::Vector(_))
+    }
+
+    /// The content decoded for reading, when it can be.
+    pub fn decoded(&self) -> Option<String> {
+        match self {
+            Content::Text(text) => Some(text.clone()),
+            Content::Vector(floats) => Some(format_floats(floats)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Content {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Content::Text(_) => write!(f, "UTF-8 text"),
+            Content::Png => write!(f, "PNG image"),
+            Content::Jpeg => write!(f, "JPEG image"),
+            Content::Vector(floats) => write!(f, "float32 vector, {} dims", floats.len()),
+            Content::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+/// Guesses what a blob holds from its magic number or its bytes.
+pub fn detect(bytes: &[u8]) -> Content {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Content::Png;
+    }
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Content::Jpeg;
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        if !text.is_empty()
+            && !text
+                .chars()
+                .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+        {
+            return Content::Text(text.to_string());
+        }
+    }
+    vector(bytes).map_or(Content::Binary, Content::Vector)
+}
+
+/// Decodes a float32 vector, only taking blobs of at least 4 floats that all
+/// look like embedding values, not all zero, so that other binary rarely
+/// passes for one.
+fn vector(bytes: &[u8]) -> Option<Vec<f32>> {
+    if bytes.len() < 16 || !bytes.len().is_multiple_of(4) {
+        return None;
+    }
+    floats(bytes)
+}
+
+/// The first 4 floats of a blob that looks like a vector, only reading its
+/// length and first 16 bytes so that it's cheap enough for every frame of the
+/// results.
+pub fn vector_head(bytes: &[u8]) -> Option<Vec<f32>> {
+    if bytes.len() < 16 || !bytes.len().is_multiple_of(4) {
+        return None;
+    }
+    floats(&bytes[..16])
+}
+
+fn floats(bytes: &[u8]) -> Option<Vec<f32>> {
+    let floats = bytes
+        .chunks_exact(4)
+        .map(|chunk| {
+            let x = f32::from_le_bytes(chunk.try_into().ok()?);
+            (x == 0.0 || (1e-6..=1e6).contains(&x.abs())).then_some(x)
+        })
+        .collect::<Option<Vec<_>>>()?;
+    floats.iter().any(|&x| x != 0.0).then_some(floats)
+}
+
+/// Floats as an array, the way `vector_extract` shows them.
+pub fn format_floats(floats: &[f32]) -> String {
+    let items = floats
+        .iter()
+        .map(|x| format!("{x:?}"))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("[{items}]")
+}
+
+/// Lines of a hex dump with offsets, hex bytes and their ASCII, like
+/// `hexdump -C`.
+pub fn hex_dump(bytes: &[u8], lines: Range<usize>) -> Vec<String> {
+    bytes
+        .chunks(DUMP_WIDTH)
+        .enumerate()
+        .skip(lines.start)
+        .take(lines.len())
+        .map(|(i, chunk)| {
+            let mut hex = String::new();
+            for j in 0..DUMP_WIDTH {
+                if j == DUMP_WIDTH / 2 {
+                    hex.push(' ');
+                }
+                match chunk.get(j) {
+                    Some(b) => hex.push_str(&format!("{b:02x} ")),
+                    None => hex.push_str("   "),
+                }
+            }
+            let ascii = chunk
+                .iter()
+                .map(|&b| match b {
+                    0x20..=0x7E => b as char,
+                    _ => '.',
+                })
+                .collect::<String>();
+            format!("{:08x}  {hex} |{ascii}|", i * DUMP_WIDTH)
+        })
+        .collect()
+}
+
+/// Number of lines of the hex dump of `len` bytes.
+pub fn dump_lines(len: usize) -> usize {
+    len.div_ceil(DUMP_WIDTH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(b"\x89PNG\r\n\x1a\n\0\0"), Content::Png);
+        assert_eq!(detect(&[0xFF, 0xD8, 0xFF, 0xE0]), Content::Jpeg);
+        assert_eq!(
+            detect(b"caf\xc3\xa9\n"),
+            Content::Text("café\n".to_string())
+        );
+        assert_eq!(detect(b"\0\x01\x02"), Content::Binary);
+
+        let floats = [0.5f32, -1.0, 0.0, 3.25];
+        let bytes = floats
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(detect(&bytes), Content::Vector(floats.to_vec()));
+        assert_eq!(format_floats(&floats), "[0.5,-1.0,0.0,3.25]");
+
+        // Too few floats, values no embedding holds, or only zeros.
+        assert_eq!(detect(&bytes[..8]), Content::Binary);
+        assert_eq!(detect(&[0xFF; 16]), Content::Binary);
+        assert_eq!(detect(&[0; 16]), Content::Binary);
+        assert_eq!(vector_head(&[0; 32]), None);
+
+        let mut long = bytes.clone();
+        long.extend([0xFF; 4]);
+        assert_eq!(vector_head(&long), Some(floats.to_vec()));
+        assert_eq!(detect(&long), Content::Binary);
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        let bytes = (0x41..0x41 + 20).collect::<Vec<u8>>();
+        assert_eq!(dump_lines(bytes.len()), 2);
+        assert_eq!(
+            hex_dump(&bytes, 0..2),
+            vec![
+                "00000000  41 42 43 44 45 46 47 48  49 4a 4b 4c 4d 4e 4f 50  |ABCDEFGHIJKLMNOP|",
+                "00000010  51 52 53 54                                       |QRST|",
+            ]
+        );
+        assert_eq!(hex_dump(&bytes, 1..5).len(), 1);
+    }
+}
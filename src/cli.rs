@@ -0,0 +1,116 @@
+use crate::{
+    config::{self, Profile},
+    db::Target,
+};
+use clap::Parser;
+use std::time::Duration;
+
+const URL_VAR: &str = "LIBSQL_URL";
+const AUTH_TOKEN_VAR: &str = "LIBSQL_AUTH_TOKEN";
+const DB_NAME_VAR: &str = "LIBSQL_DB_NAME";
+
+/// A query runner TUI for LibSQL databases.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Args {
+    /// Path to a local database file, or `:memory:`
+    #[arg(conflicts_with_all = ["url", "db", "profile"])]
+    pub path: Option<String>,
+
+    /// Name of a profile from the libsqltui config file
+    #[arg(long, short, conflicts_with_all = ["url", "db"])]
+    pub profile: Option<String>,
+
+    /// Database URL, e.g. libsql://my-db.turso.io [env: LIBSQL_URL]
+    #[arg(long, short, conflicts_with = "db")]
+    pub url: Option<String>,
+
+    /// Auth token used along with --url [env: LIBSQL_AUTH_TOKEN]
+    #[arg(long, short = 't')]
+    pub auth_token: Option<String>,
+
+    /// Name of a database from the Turso CLI cache [env: LIBSQL_DB_NAME]
+    #[arg(long, short)]
+    pub db: Option<String>,
+
+    /// Open the remote database as an embedded replica stored at this path
+    #[arg(long, short, value_name = "FILE", conflicts_with = "path")]
+    pub replica: Option<String>,
+
+    /// Sync the embedded replica every N seconds
+    #[arg(long, value_name = "SECS", requires = "replica")]
+    pub sync_interval: Option<u64>,
+
+    /// Give up on queries running longer than N seconds
+    #[arg(long, value_name = "SECS")]
+    pub timeout: Option<u64>,
+
+    /// Rows fetched at a time, more are loaded on demand
+    #[arg(long, value_name = "ROWS", default_value_t = 1000)]
+    pub page_size: usize,
+}
+
+impl Args {
+    /// Resolves the connection profile, falling back to the interactive
+    /// database picker when nothing was specified.
+    ///
+    /// Flags given on the command line take precedence over the environment.
+    pub fn profile(&self) -> anyhow::Result<Profile> {
+        if let Some(path) = &self.path {
+            return Ok(Profile::local(path.clone()));
+        }
+
+        let url = match (&self.url, &self.db, &self.profile) {
+            (Some(url), _, _) => Some(url.clone()),
+            (None, None, None) => env_var(URL_VAR),
+            _ => None,
+        };
+        if let Some(url) = url {
+            let token = self.auth_token.clone().or_else(|| env_var(AUTH_TOKEN_VAR));
+            return Ok(Profile::remote(url, token));
+        }
+
+        if let Some(name) = &self.profile {
+            return config::load_app_config()?
+                .profiles
+                .into_iter()
+                .find(|p| &p.name == name)
+                .ok_or(anyhow::anyhow!("No profile named {name}"));
+        }
+
+        let Some(name) = self.db.clone().or_else(|| env_var(DB_NAME_VAR)) else {
+            return config::select_database(&config::load_entries()?);
+        };
+        let config = config::load_config()?;
+        let db = config.find_database(&name)?;
+        match &self.auth_token {
+            Some(token) => Ok(Profile {
+                name: db.name.clone(),
+                ..Profile::remote(db.url(), Some(token.clone()))
+            }),
+            None => config.profile(db),
+        }
+    }
+
+    /// Builds the target for the profile, opening it as an embedded replica
+    /// when asked to.
+    pub fn target(&self, profile: &Profile) -> anyhow::Result<Target> {
+        match (profile.target()?, &self.replica) {
+            (Target::Remote { url, token }, Some(path)) => Ok(Target::Replica {
+                path: path.clone(),
+                url,
+                token,
+            }),
+            (_, Some(_)) => anyhow::bail!("--replica requires a remote database"),
+            (target, None) => Ok(target),
+        }
+    }
+
+    pub fn sync_interval(&self) -> Option<Duration> {
+        self.sync_interval.map(Duration::from_secs)
+    }
+}
+
+fn env_var(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.is_empty())
+}
@@ -1,8 +1,141 @@
+use crate::{db::Target, token};
 use anyhow::Context;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
 
 const APP_IDENTIFIER: &str = "turso";
+const PROFILES_IDENTIFIER: &str = "libsqltui";
+/// Longest a token command runs, one waiting on a login never returning.
+const TOKEN_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The app's own config file, holding named connection profiles.
+#[derive(Debug, Default, Deserialize)]
+pub struct AppConfig {
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+}
+
+/// A named connection, either saved in the app config or derived from the
+/// Turso CLI cache.
+#[derive(Debug, Default, Deserialize, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    pub url: Option<String>,
+    pub path: Option<String>,
+    pub token: Option<String>,
+    /// Shell command printing the auth token, run when connecting.
+    pub token_command: Option<String>,
+    #[serde(default)]
+    pub read_only: bool,
+    /// Color of the database bar, e.g. `red` or `#ff8800`.
+    pub color: Option<String>,
+    /// Give up on queries running longer than this many seconds.
+    pub timeout_secs: Option<u64>,
+}
+
+impl Profile {
+    pub fn local(path: String) -> Self {
+        Self {
+            name: path.clone(),
+            path: Some(path),
+            ..Default::default()
+        }
+    }
+
+    pub fn remote(url: String, token: Option<String>) -> Self {
+        Self {
+            name: url.clone(),
+            url: Some(url),
+            token,
+            ..Default::default()
+        }
+    }
+
+    pub fn location(&self) -> &str {
+        self.url.as_deref().or(self.path.as_deref()).unwrap_or("")
+    }
+
+    /// Resolves the token of remote profiles, failing when it's expired.
+    pub fn target(&self) -> anyhow::Result<Target> {
+        match (&self.url, &self.path) {
+            (Some(url), None) => {
+                let token = self.resolve_token()?;
+                token::validate(&self.name, &token)?;
+                Ok(Target::Remote {
+                    url: url.clone(),
+                    token,
+                })
+            }
+            (None, Some(path)) => Ok(Target::Local { path: path.clone() }),
+            _ => anyhow::bail!("Profile {} needs exactly one of `url` or `path`", self.name),
+        }
+    }
+
+    /// Connecting runs the token command, which may take a while.
+    pub fn runs_token_command(&self) -> bool {
+        self.url.is_some() && self.token.is_none() && self.token_command.is_some()
+    }
+
+    fn resolve_token(&self) -> anyhow::Result<String> {
+        if let Some(token) = &self.token {
+            return Ok(token.clone());
+        }
+        let Some(command) = &self.token_command else {
+            return Ok(String::new());
+        };
+
+        // The terminal belongs to the app, the command gets no input.
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run token command for {}", self.name))?;
+        let started = Instant::now();
+        while child.try_wait()?.is_none() {
+            if started.elapsed() > TOKEN_COMMAND_TIMEOUT {
+                let _ = child.kill();
+                let _ = child.wait();
+                anyhow::bail!(
+                    "Token command for {} timed out after {}s",
+                    self.name,
+                    TOKEN_COMMAND_TIMEOUT.as_secs()
+                );
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Token command for {} failed: {}",
+                self.name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// A database listed in the pickers.
+pub struct Entry {
+    pub name: String,
+    pub profile: Result<Profile, String>,
+}
+
+impl Entry {
+    /// Location of the database, told apart by it rather than by its name
+    /// which saved profiles and Turso databases may share.
+    pub fn location(&self) -> Option<&str> {
+        self.profile.as_ref().ok().map(Profile::location)
+    }
+}
 
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
@@ -16,8 +149,8 @@ pub struct Cache {
     pub database_token: Option<HashMap<String, DatabaseToken>>,
 }
 #[derive(Debug, Deserialize)]
-#[allow(dead_code)]
 pub struct DatabaseToken {
+    /// Unix timestamp the Turso CLI recorded, `0` when it never expires.
     pub expiration: u64,
     pub data: String,
 }
@@ -37,6 +170,53 @@ pub struct DatabaseName {
     pub hostname: String,
 }
 
+impl DatabaseName {
+    pub fn url(&self) -> String {
+        format!("libsql://{}", self.hostname)
+    }
+}
+
+impl Config {
+    pub fn database_names(&self) -> anyhow::Result<&DatabaseNames> {
+        self.cache.database_names.as_ref().ok_or(anyhow::anyhow!(
+            "No database names, please run `turso db list`"
+        ))
+    }
+
+    pub fn find_database(&self, name: &str) -> anyhow::Result<&DatabaseName> {
+        self.database_names()?
+            .data
+            .iter()
+            .find(|d| d.name == name)
+            .ok_or(anyhow::anyhow!(
+                "No database named {name}, please run `turso db list`"
+            ))
+    }
+
+    pub fn token_for(&self, db: &DatabaseName) -> anyhow::Result<&DatabaseToken> {
+        let db_tokens = self.cache.database_token.as_ref().ok_or(anyhow::anyhow!(
+            "No database tokens found in config, use `turso db shell DB_NAME` to populate the config",
+        ))?;
+
+        let db_token = db_tokens.get(db.db_id.as_str()).ok_or(anyhow::anyhow!(
+            "No database token found for {}, use `turso db shell {}` to populate the config",
+            db.name,
+            db.name
+        ))?;
+
+        // The `exp` claim of the token is checked too when connecting.
+        token::check_expiration(&db.name, db_token.expiration)?;
+        Ok(db_token)
+    }
+
+    pub fn profile(&self, db: &DatabaseName) -> anyhow::Result<Profile> {
+        Ok(Profile {
+            name: db.name.clone(),
+            ..Profile::remote(db.url(), Some(self.token_for(db)?.data.clone()))
+        })
+    }
+}
+
 pub fn load_config() -> anyhow::Result<Config> {
     let path = dirs::config_dir().ok_or(anyhow::anyhow!("No config dir"))?;
     let path = path.join(APP_IDENTIFIER);
@@ -46,16 +226,53 @@ pub fn load_config() -> anyhow::Result<Config> {
     Ok(serde_json::from_str(&config)?)
 }
 
-pub fn select_database(config: &Config) -> anyhow::Result<&DatabaseName> {
-    let database_names = config.cache.database_names.as_ref().ok_or(anyhow::anyhow!(
-        "No database names, please run `turso db list`"
-    ))?;
-    let databases: Vec<&str> = database_names
-        .data
-        .iter()
-        .map(|d| d.name.as_str())
+pub fn load_app_config() -> anyhow::Result<AppConfig> {
+    let path = dirs::config_dir().ok_or(anyhow::anyhow!("No config dir"))?;
+    let path = path.join(PROFILES_IDENTIFIER);
+    let path = path.join("config.json");
+
+    if !path.exists() {
+        return Ok(AppConfig::default());
+    }
+    let config = std::fs::read_to_string(&path)?;
+    serde_json::from_str(&config).with_context(|| format!("Invalid config {}", path.display()))
+}
+
+/// Lists the saved profiles followed by the databases from the Turso CLI
+/// cache. The Turso cache is optional as long as there are profiles.
+pub fn load_entries() -> anyhow::Result<Vec<Entry>> {
+    let mut entries = load_app_config()?
+        .profiles
+        .into_iter()
+        .map(|profile| Entry {
+            name: profile.name.clone(),
+            profile: Ok(profile),
+        })
         .collect::<Vec<_>>();
 
+    let turso = load_config().and_then(|config| {
+        Ok(config
+            .database_names()?
+            .data
+            .iter()
+            .map(|db| Entry {
+                name: db.name.clone(),
+                profile: config.profile(db).map_err(|err| err.to_string()),
+            })
+            .collect::<Vec<_>>())
+    });
+    match turso {
+        Ok(turso) => entries.extend(turso),
+        Err(err) if entries.is_empty() => return Err(err),
+        Err(_) => {}
+    }
+
+    Ok(entries)
+}
+
+pub fn select_database(entries: &[Entry]) -> anyhow::Result<Profile> {
+    let databases: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect::<Vec<_>>();
+
     let selected_database =
         dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
             .with_prompt("Select database")
@@ -63,5 +280,8 @@ pub fn select_database(config: &Config) -> anyhow::Result<&DatabaseName> {
             .default(0)
             .interact()?;
 
-    Ok(&database_names.data[selected_database])
+    entries[selected_database]
+        .profile
+        .clone()
+        .map_err(anyhow::Error::msg)
 }
@@ -1,48 +1,352 @@
-use anyhow::Result;
-use libsql::{Connection, Rows, Value};
-use std::{fmt::Display, ops::Deref};
+use crate::{blob, sql};
+use anyhow::{Context, Result};
+use libsql::{params::IntoParams, Connection, Database, Rows, Transaction, Value};
+use std::{
+    fmt::Display,
+    ops::Deref,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use unicode_width::UnicodeWidthStr;
+use url::Url;
 
-#[derive(Debug, Clone)]
-pub struct LibSqlClient(pub Connection);
+/// Where a connection points to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Remote {
+        url: String,
+        token: String,
+    },
+    Local {
+        path: String,
+    },
+    Replica {
+        path: String,
+        url: String,
+        token: String,
+    },
+}
 
-impl LibSqlClient {
-    pub async fn query_owned(&self, sql: &str) -> Result<Table> {
-        let mut rows: Rows = self.query(sql, ()).await?;
+impl Target {
+    pub async fn connect(&self) -> Result<LibSqlClient> {
+        let db = match self {
+            Target::Remote { url, token } => {
+                libsql::Builder::new_remote(normalize_url(url)?, token.clone())
+                    .build()
+                    .await?
+            }
+            Target::Local { path } => {
+                if path != ":memory:" && !Path::new(path).exists() {
+                    anyhow::bail!("Database file {path} does not exist");
+                }
+                libsql::Builder::new_local(path)
+                    .build()
+                    .await
+                    .with_context(|| format!("Failed to open {path}"))?
+            }
+            Target::Replica { path, url, token } => {
+                libsql::Builder::new_remote_replica(path, normalize_url(url)?, token.clone())
+                    .build()
+                    .await
+                    .with_context(|| format!("Failed to open replica {path}"))?
+            }
+        };
+        let conn = db.connect()?;
 
-        let col_cnt = rows.column_count();
-        let mut cols = Vec::with_capacity(col_cnt as usize);
-        for i in 0..col_cnt {
-            cols.push(rows.column_name(i).unwrap_or("").to_owned());
+        Ok(LibSqlClient {
+            db: Arc::new(db),
+            conn,
+            read_only: false,
+            decl_types: !matches!(self, Target::Remote { .. }),
+            local: matches!(self, Target::Local { .. }),
+            running: Arc::default(),
+        })
+    }
+}
+
+impl Target {
+    pub fn token(&self) -> Option<&str> {
+        match self {
+            Target::Remote { token, .. } | Target::Replica { token, .. } => Some(token),
+            Target::Local { .. } => None,
         }
+    }
+}
 
+impl Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Target::Remote { url, .. } => write!(f, "{url}"),
+            Target::Local { path } => write!(f, "{path}"),
+            Target::Replica { path, url, .. } => write!(f, "{url} (replica at {path})"),
+        }
+    }
+}
+
+/// Maps the URL schemes we accept onto the ones the libsql remote client
+/// speaks, so self-hosted `sqld` instances work alongside Turso.
+///
+/// A bare hostname is treated as a Turso database, and `libsql://` URLs with
+/// `?tls=0` are served over plain HTTP, as in the official clients.
+pub fn normalize_url(url: &str) -> Result<String> {
+    if !url.contains("://") {
+        return Ok(format!("libsql://{url}"));
+    }
+
+    let mut parsed = Url::parse(url).with_context(|| format!("Invalid database URL {url}"))?;
+    let no_tls = parsed.query_pairs().any(|(k, v)| k == "tls" && v == "0");
+
+    let scheme = match parsed.scheme() {
+        "libsql" if no_tls => "http",
+        "libsql" | "https" | "http" => return Ok(url.to_string()),
+        "wss" => "https",
+        "ws" => "http",
+        other => {
+            anyhow::bail!("Unsupported URL scheme {other}://, expected libsql, http(s) or ws(s)")
+        }
+    };
+    parsed.set_query(None);
+
+    // `Url::set_scheme` refuses to switch between special and non-special
+    // schemes, so the scheme is swapped on the serialized form instead.
+    let rest = &parsed.as_str()[parsed.scheme().len()..];
+    Ok(format!("{scheme}{rest}"))
+}
+
+#[derive(Clone)]
+pub struct LibSqlClient {
+    db: Arc<Database>,
+    conn: Connection,
+    read_only: bool,
+    /// Declared column types can be read, remote connections don't report
+    /// them.
+    decl_types: bool,
+    /// Statements run in-process, blocking their thread, where remote and
+    /// replica ones are requests that can't be interrupted.
+    local: bool,
+    /// Statements running on the connection, counted across the clones of
+    /// the client.
+    running: Arc<AtomicUsize>,
+}
+
+/// Outcome of syncing an embedded replica with its remote.
+#[derive(Debug, Clone, Copy)]
+pub struct SyncInfo {
+    pub frame_no: Option<u64>,
+    pub frames_synced: usize,
+}
+
+/// Outcome of a statement that doesn't return rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecuteInfo {
+    pub rows_affected: u64,
+    /// Only for inserts that inserted rows, it's left over from an earlier
+    /// statement otherwise.
+    pub last_insert_rowid: Option<i64>,
+}
+
+/// What running a statement produced.
+#[derive(Debug)]
+pub enum Output {
+    /// The first rows, along with a cursor when more are left to fetch.
+    Rows(Table, Option<Cursor>),
+    Execute(ExecuteInfo),
+}
+
+/// Rows of a query left to fetch.
+pub struct Cursor {
+    rows: Rows,
+}
+
+impl Cursor {
+    /// Fetches up to `limit` rows, and whether more may be left.
+    pub async fn fetch(&mut self, limit: usize) -> Result<(Vec<Vec<ValueWrapper>>, bool)> {
+        let col_cnt = self.rows.column_count();
         let mut out_rows = Vec::new();
-        while let Some(row) = rows.next().await? {
+        while out_rows.len() < limit {
+            let Some(row) = self.rows.next().await? else {
+                return Ok((out_rows, false));
+            };
             let mut vals = Vec::with_capacity(col_cnt as usize);
             for i in 0..col_cnt {
                 vals.push(ValueWrapper(row.get_value(i)?));
             }
             out_rows.push(vals);
         }
+        Ok((out_rows, true))
+    }
+}
+
+impl std::fmt::Debug for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cursor").finish_non_exhaustive()
+    }
+}
+
+impl LibSqlClient {
+    /// Rejects statements that write, see [`sql::is_read_only`]. Local
+    /// databases also enforce it themselves with `PRAGMA query_only`.
+    pub async fn with_read_only(mut self, read_only: bool) -> Result<Self> {
+        if read_only && self.local {
+            self.execute("PRAGMA query_only = ON", ()).await?;
+        }
+        self.read_only = read_only;
+        Ok(self)
+    }
+
+    /// Client running its statements in the transaction.
+    pub fn in_transaction(&self, tx: &Transaction) -> Self {
+        Self {
+            conn: Connection::clone(tx),
+            ..self.clone()
+        }
+    }
+
+    pub async fn sync(&self) -> Result<SyncInfo> {
+        let replicated = self.db.sync().await?;
 
-        Ok(Table {
-            columns: cols,
-            rows: out_rows,
+        Ok(SyncInfo {
+            frame_no: replicated.frame_no(),
+            frames_synced: replicated.frames_synced(),
         })
     }
+
+    /// Runs the statement with its parameters, executing it when it doesn't
+    /// return rows, see [`sql::returns_rows`]. At most `limit` rows are
+    /// fetched upfront.
+    pub async fn run(&self, sql: &str, params: Vec<Value>, limit: usize) -> Result<Output> {
+        self.running.fetch_add(1, Ordering::SeqCst);
+        let _running = Running(&self.running);
+
+        if sql::returns_rows(sql) {
+            let (table, cursor) = self.query_owned(sql, params, limit).await?;
+            Ok(Output::Rows(table, cursor))
+        } else {
+            self.execute_owned(sql, params).await.map(Output::Execute)
+        }
+    }
+
+    /// Whether statements run in-process, blocking their thread.
+    pub fn is_local(&self) -> bool {
+        self.local
+    }
+
+    /// Interrupts the running statement, unless other statements run on the
+    /// connection too as interrupting would stop all of them.
+    pub fn interrupt_alone(&self) -> Result<()> {
+        if self.running.load(Ordering::SeqCst) <= 1 {
+            self.interrupt()?;
+        }
+        Ok(())
+    }
+
+    pub async fn execute_owned(&self, sql: &str, params: impl IntoParams) -> Result<ExecuteInfo> {
+        self.check_writable(sql)?;
+
+        let rows_affected = self.execute(sql, params).await?;
+        Ok(ExecuteInfo {
+            rows_affected,
+            last_insert_rowid: (rows_affected > 0 && sql::is_insert(sql))
+                .then(|| self.last_insert_rowid()),
+        })
+    }
+
+    fn check_writable(&self, sql: &str) -> Result<()> {
+        if self.read_only && !sql::is_read_only(sql) {
+            anyhow::bail!("Connection is read-only");
+        }
+        Ok(())
+    }
+
+    /// Fetches the first `limit` rows, returning a cursor over the rest when
+    /// there may be more.
+    pub async fn query_owned(
+        &self,
+        sql: &str,
+        params: impl IntoParams,
+        limit: usize,
+    ) -> Result<(Table, Option<Cursor>)> {
+        self.check_writable(sql)?;
+
+        let (rows, decl_types): (Rows, _) = if self.decl_types {
+            let mut stmt = self.prepare(sql).await?;
+            let decl_types = stmt
+                .columns()
+                .iter()
+                .map(|c| c.decl_type().map(str::to_owned))
+                .collect();
+            (stmt.query(params).await?, decl_types)
+        } else {
+            (self.query(sql, params).await?, vec![])
+        };
+
+        let col_cnt = rows.column_count();
+        let mut cols = Vec::with_capacity(col_cnt as usize);
+        for i in 0..col_cnt {
+            cols.push(rows.column_name(i).unwrap_or("").to_owned());
+        }
+
+        let mut cursor = Cursor { rows };
+        let (out_rows, more) = cursor.fetch(limit).await?;
+
+        let table = Table::new(cols, decl_types, out_rows, more);
+        Ok((table, more.then_some(cursor)))
+    }
+}
+
+/// Counts a statement as running until dropped.
+struct Running<'a>(&'a AtomicUsize);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 #[derive(Debug)]
 pub struct ValueWrapper(Value);
 
+impl ValueWrapper {
+    pub fn value(&self) -> &Value {
+        &self.0
+    }
+
+    /// The value in full, where `Display` shortens blobs.
+    pub fn full(&self) -> String {
+        match &self.0 {
+            Value::Blob(bytes) => bytes
+                .iter()
+                .map(|b| format!("{b:02X}"))
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => self.to_string(),
+        }
+    }
+}
+
 impl Display for ValueWrapper {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let inner = &self.0;
         match inner {
             Value::Null => write!(f, "NULL"),
             Value::Integer(i) => write!(f, "{i}"),
-            Value::Real(x) => write!(f, "{x}"),
+            // Debug keeps the fraction of whole reals, telling them apart
+            // from integers.
+            Value::Real(x) => write!(f, "{x:?}"),
             Value::Text(s) => write!(f, "{s}"),
             Value::Blob(bytes) => {
+                if let Some(head) = blob::vector_head(bytes) {
+                    let dims = bytes.len() / 4;
+                    let shown = blob::format_floats(&head);
+                    if dims == head.len() {
+                        return write!(f, "{shown} ({dims} dims)");
+                    }
+                    let shown = shown.trim_end_matches(']');
+                    return write!(f, "{shown}…] ({dims} dims)");
+                }
                 let shown = bytes
                     .iter()
                     .take(16)
@@ -63,12 +367,209 @@ impl Deref for LibSqlClient {
     type Target = Connection;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.conn
     }
 }
 
+/// Rows looked at to size the result columns.
+const WIDTH_SAMPLE_ROWS: usize = 200;
+
 #[derive(Debug)]
 pub struct Table {
     pub columns: Vec<String>,
+    /// Declared type of each column, empty when unknown.
+    pub decl_types: Vec<Option<String>>,
     pub rows: Vec<Vec<ValueWrapper>>,
+    /// Rows may be left to fetch.
+    pub more: bool,
+    /// Width of the widest cell of each column among the first rows,
+    /// measured as they arrive rather than on every render.
+    pub cell_widths: Vec<usize>,
+}
+
+impl Table {
+    pub fn new(
+        columns: Vec<String>,
+        decl_types: Vec<Option<String>>,
+        rows: Vec<Vec<ValueWrapper>>,
+        more: bool,
+    ) -> Self {
+        let mut table = Self {
+            cell_widths: vec![0; columns.len()],
+            columns,
+            decl_types,
+            rows: vec![],
+            more,
+        };
+        table.extend(rows);
+        table
+    }
+
+    /// Appends fetched rows.
+    pub fn extend(&mut self, rows: Vec<Vec<ValueWrapper>>) {
+        let sampled = WIDTH_SAMPLE_ROWS.saturating_sub(self.rows.len());
+        for row in rows.iter().take(sampled) {
+            for (width, cell) in self.cell_widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.to_string().width());
+            }
+        }
+        self.rows.extend(rows);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_memory() {
+        let target = Target::Local {
+            path: ":memory:".to_string(),
+        };
+        let client = target.connect().await.unwrap();
+
+        let (table, _) = client
+            .query_owned("SELECT 1 AS one", (), 1000)
+            .await
+            .unwrap();
+        assert_eq!(table.columns, vec!["one"]);
+        assert_eq!(table.rows.len(), 1);
+        assert_eq!(table.rows[0][0].to_string(), "1");
+    }
+
+    #[tokio::test]
+    async fn test_local_read_only() {
+        let target = Target::Local {
+            path: ":memory:".to_string(),
+        };
+        let client = target.connect().await.unwrap();
+        let client = client.with_read_only(true).await.unwrap();
+
+        assert!(client.query_owned("SELECT 1", (), 1).await.is_ok());
+        // Slips past `sql::is_read_only`, the database refuses it.
+        assert!(client
+            .execute_owned("PRAGMA user_version(3)", ())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_decl_types() {
+        let target = Target::Local {
+            path: ":memory:".to_string(),
+        };
+        let client = target.connect().await.unwrap();
+        client
+            .run("CREATE TABLE t (a INTEGER, b)", vec![], 1)
+            .await
+            .unwrap();
+
+        let (table, _) = client
+            .query_owned("SELECT a, b, 1.0 AS c FROM t", (), 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            table.decl_types,
+            vec![Some("INTEGER".to_string()), None, None]
+        );
+        assert_eq!(ValueWrapper(Value::Real(1.0)).to_string(), "1.0");
+    }
+
+    #[test]
+    fn test_cell_widths() {
+        let row = |text: &str| vec![ValueWrapper(Value::Text(text.to_string()))];
+        let mut table = Table::new(vec!["a".to_string()], vec![], vec![row("abc")], true);
+        assert_eq!(table.cell_widths, vec![3]);
+
+        table.extend(vec![row("abcdé")]);
+        assert_eq!(table.cell_widths, vec![5]);
+        table.extend((0..WIDTH_SAMPLE_ROWS).map(|_| row("a")).collect());
+        table.extend(vec![row(&"a".repeat(10))]);
+        assert_eq!(table.cell_widths, vec![5]);
+    }
+
+    #[test]
+    fn test_value_full() {
+        let blob = ValueWrapper(Value::Blob((0..20).collect()));
+        assert!(blob.to_string().ends_with("0F … (20 bytes)"));
+        assert!(blob.full().ends_with("0F 10 11 12 13"));
+
+        let floats = [0.5f32, 1.0, 2.0, 3.0, 4.0];
+        let vector = ValueWrapper(Value::Blob(
+            floats.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        ));
+        assert_eq!(vector.to_string(), "[0.5,1.0,2.0,3.0…] (5 dims)");
+        let vector = ValueWrapper(Value::Blob(
+            floats[..4].iter().flat_map(|x| x.to_le_bytes()).collect(),
+        ));
+        assert_eq!(vector.to_string(), "[0.5,1.0,2.0,3.0] (4 dims)");
+
+        let text = ValueWrapper(Value::Text("a\nb".to_string()));
+        assert_eq!(text.full(), "a\nb");
+    }
+
+    #[tokio::test]
+    async fn test_run_execute() {
+        let target = Target::Local {
+            path: ":memory:".to_string(),
+        };
+        let client = target.connect().await.unwrap();
+        client.run("CREATE TABLE t (a)", vec![], 1).await.unwrap();
+
+        let Output::Execute(info) = client
+            .run(
+                "INSERT INTO t VALUES (?), (:b)",
+                vec![1.into(), 2.into()],
+                1,
+            )
+            .await
+            .unwrap()
+        else {
+            panic!("expected an execute output");
+        };
+        assert_eq!(info.rows_affected, 2);
+        assert_eq!(info.last_insert_rowid, Some(2));
+
+        let Output::Execute(info) = client
+            .run("UPDATE t SET a = a + 1", vec![], 1)
+            .await
+            .unwrap()
+        else {
+            panic!("expected an execute output");
+        };
+        assert_eq!(info.rows_affected, 2);
+        assert_eq!(info.last_insert_rowid, None);
+
+        let Output::Rows(table, Some(mut cursor)) = client
+            .run("DELETE FROM t RETURNING a", vec![], 1)
+            .await
+            .unwrap()
+        else {
+            panic!("expected rows left to fetch");
+        };
+        assert_eq!(table.rows.len(), 1);
+        assert!(table.more);
+
+        let (rows, more) = cursor.fetch(10).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert!(!more);
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        let cases = [
+            ("my-db.turso.io", "libsql://my-db.turso.io"),
+            ("libsql://my-db.turso.io", "libsql://my-db.turso.io"),
+            ("libsql://127.0.0.1:8080?tls=0", "http://127.0.0.1:8080"),
+            ("http://127.0.0.1:8080", "http://127.0.0.1:8080"),
+            ("https://my-db.turso.io", "https://my-db.turso.io"),
+            ("ws://localhost:8080", "http://localhost:8080/"),
+            ("wss://my-db.turso.io", "https://my-db.turso.io/"),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(normalize_url(url).unwrap(), expected);
+        }
+
+        assert!(normalize_url("postgres://localhost").is_err());
+    }
 }
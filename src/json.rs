@@ -0,0 +1,385 @@
+use ratatui::{
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+/// Step from a JSON value to one of its children.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Line of a pretty-printed JSON document.
+#[derive(Debug)]
+pub struct JsonLine {
+    /// Node the line shows, the closing line of an object or array
+    /// belonging to it too.
+    pub path: Vec<Segment>,
+    pub line: Line<'static>,
+}
+
+/// Pretty-printed JSON document whose objects and arrays can be collapsed.
+#[derive(Debug)]
+pub struct Viewer {
+    root: Value,
+    /// Source text of the numbers, which `Value` rounds to an `f64`.
+    numbers: HashMap<Vec<Segment>, String>,
+    collapsed: HashSet<Vec<Segment>>,
+    lines: Vec<JsonLine>,
+    pub cursor: usize,
+}
+
+impl Viewer {
+    /// Parses a JSON object or array, other values not being worth a tree.
+    pub fn parse(text: &str) -> Option<Self> {
+        let root: Value = serde_json::from_str(text).ok()?;
+        if !root.is_object() && !root.is_array() {
+            return None;
+        }
+
+        let mut viewer = Self {
+            root,
+            numbers: raw_numbers(text),
+            collapsed: HashSet::new(),
+            lines: vec![],
+            cursor: 0,
+        };
+        viewer.layout();
+        Some(viewer)
+    }
+
+    pub fn lines(&self) -> &[JsonLine] {
+        &self.lines
+    }
+
+    /// Path of the node under the cursor.
+    pub fn path(&self) -> &[Segment] {
+        self.lines.get(self.cursor).map_or(&[], |l| &l.path)
+    }
+
+    pub fn move_cursor(&mut self, delta: isize) {
+        self.cursor = self
+            .cursor
+            .saturating_add_signed(delta)
+            .min(self.lines.len().saturating_sub(1));
+    }
+
+    /// Collapses or expands the object or array under the cursor, or the one
+    /// holding the value under the cursor.
+    pub fn toggle(&mut self) {
+        let mut path = self.path().to_vec();
+        if !node(&self.root, &path).is_some_and(|v| v.is_object() || v.is_array()) {
+            path.pop();
+        }
+
+        if !self.collapsed.remove(&path) {
+            self.collapsed.insert(path.clone());
+        }
+        self.layout();
+        self.cursor = self.lines.iter().position(|l| l.path == path).unwrap_or(0);
+    }
+
+    fn layout(&mut self) {
+        self.lines.clear();
+        push_lines(
+            &self.root,
+            &mut vec![],
+            vec![],
+            false,
+            (&self.collapsed, &self.numbers),
+            &mut self.lines,
+        );
+    }
+}
+
+fn node<'a>(root: &'a Value, path: &[Segment]) -> Option<&'a Value> {
+    path.iter().try_fold(root, |value, segment| match segment {
+        Segment::Key(key) => value.get(key),
+        Segment::Index(i) => value.get(i),
+    })
+}
+
+/// Pushes the lines showing `value`, with `label` before it.
+fn push_lines(
+    value: &Value,
+    path: &mut Vec<Segment>,
+    label: Vec<Span<'static>>,
+    comma: bool,
+    (collapsed, numbers): (&HashSet<Vec<Segment>>, &HashMap<Vec<Segment>, String>),
+    lines: &mut Vec<JsonLine>,
+) {
+    let indent = Span::raw("  ".repeat(path.len()));
+    let comma = Span::raw(if comma { "," } else { "" });
+    let mut line = |spans: Vec<Span<'static>>, path: &[Segment]| {
+        let mut all = vec![indent.clone()];
+        all.extend(spans);
+        all.push(comma.clone());
+        lines.push(JsonLine {
+            path: path.to_vec(),
+            line: Line::from(all),
+        });
+    };
+
+    let (open, close, len) = match value {
+        Value::Object(map) => ("{", "}", map.len()),
+        Value::Array(items) => ("[", "]", items.len()),
+        scalar => {
+            let mut spans = label;
+            spans.push(scalar_span(scalar, numbers.get(path.as_slice())));
+            line(spans, path);
+            return;
+        }
+    };
+
+    if len == 0 || collapsed.contains(path) {
+        let mut spans = label;
+        if len == 0 {
+            spans.push(Span::raw(format!("{open}{close}")));
+        } else {
+            let unit = match (value.is_object(), len) {
+                (true, 1) => "key",
+                (true, _) => "keys",
+                (false, 1) => "item",
+                (false, _) => "items",
+            };
+            spans.push(Span::raw(format!("{open}…{close}")));
+            spans.push(Span::styled(
+                format!(" {len} {unit}"),
+                Style::default().fg(Color::Indexed(242)),
+            ));
+        }
+        line(spans, path);
+        return;
+    }
+
+    // The opening line has no comma, the closing one takes it.
+    let mut spans = vec![indent.clone()];
+    spans.extend(label);
+    spans.push(Span::raw(open));
+    lines.push(JsonLine {
+        path: path.clone(),
+        line: Line::from(spans),
+    });
+
+    match value {
+        Value::Object(map) => {
+            for (i, (key, child)) in map.iter().enumerate() {
+                path.push(Segment::Key(key.clone()));
+                let label = vec![
+                    Span::styled(
+                        serde_json::to_string(key).unwrap_or_default(),
+                        Style::default().fg(Color::Blue),
+                    ),
+                    Span::raw(": "),
+                ];
+                push_lines(child, path, label, i + 1 < len, (collapsed, numbers), lines);
+                path.pop();
+            }
+        }
+        Value::Array(items) => {
+            for (i, child) in items.iter().enumerate() {
+                path.push(Segment::Index(i));
+                push_lines(
+                    child,
+                    path,
+                    vec![],
+                    i + 1 < len,
+                    (collapsed, numbers),
+                    lines,
+                );
+                path.pop();
+            }
+        }
+        _ => unreachable!(),
+    }
+
+    lines.push(JsonLine {
+        path: path.clone(),
+        line: Line::from(vec![indent, Span::raw(close), comma]),
+    });
+}
+
+fn scalar_span(value: &Value, raw: Option<&String>) -> Span<'static> {
+    let text = raw.cloned().unwrap_or_else(|| value.to_string());
+    match value {
+        Value::String(_) => Span::styled(text, Style::default().fg(Color::Green)),
+        Value::Number(_) => Span::styled(text, Style::default().fg(Color::Yellow)),
+        Value::Bool(_) => Span::styled(text, Style::default().fg(Color::Magenta)),
+        _ => Span::styled(text, Style::default().fg(Color::Indexed(242)).italic()),
+    }
+}
+
+/// Source text of the numbers of a valid JSON document, by path. A key given
+/// twice keeps its last number, like `Value` does.
+fn raw_numbers(text: &str) -> HashMap<Vec<Segment>, String> {
+    let bytes = text.as_bytes();
+    let mut numbers = HashMap::new();
+    // Segment of each open object or array, a key being awaited when none.
+    let mut path: Vec<Option<Segment>> = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => path.push(None),
+            b'[' => path.push(Some(Segment::Index(0))),
+            b'}' | b']' => {
+                path.pop();
+            }
+            b',' => match path.last_mut() {
+                Some(Some(Segment::Index(index))) => *index += 1,
+                Some(key) => *key = None,
+                None => {}
+            },
+            b'"' => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                if let Some(key @ None) = path.last_mut() {
+                    let name = serde_json::from_str(&text[start..=i.min(bytes.len() - 1)]);
+                    *key = Some(Segment::Key(name.unwrap_or_default()));
+                }
+            }
+            b'-' | b'0'..=b'9' => {
+                let start = i;
+                while i + 1 < bytes.len()
+                    && matches!(bytes[i + 1], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+                {
+                    i += 1;
+                }
+                let path = path.iter().flatten().cloned().collect();
+                numbers.insert(path, text[start..=i].to_string());
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    numbers
+}
+
+/// SQLite JSON path of a node, as taken by the `json_*` functions.
+pub fn json_path(path: &[Segment]) -> String {
+    let mut out = "$".to_string();
+    for segment in path {
+        match segment {
+            // SQLite has no escape for quotes in a quoted key, but reads a
+            // bare key up to the next `.` or `[`.
+            Segment::Key(key) if is_identifier(key) || key.contains('"') => {
+                let _ = write!(out, ".{key}");
+            }
+            Segment::Key(key) => {
+                let _ = write!(out, ".\"{key}\"");
+            }
+            Segment::Index(i) => {
+                let _ = write!(out, "[{i}]");
+            }
+        }
+    }
+    out
+}
+
+/// `json_extract` expression reading the node from a column, quoted in case
+/// its name is a keyword.
+pub fn extract_expr(column: &str, path: &[Segment]) -> String {
+    let column = format!("\"{}\"", column.replace('"', "\"\""));
+    let path = json_path(path).replace('\'', "''");
+    format!("json_extract({column}, '{path}')")
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(viewer: &Viewer) -> Vec<String> {
+        viewer.lines().iter().map(|l| l.line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_viewer() {
+        assert!(Viewer::parse("42").is_none());
+        assert!(Viewer::parse("{").is_none());
+
+        let mut viewer = Viewer::parse(r#"{"b": [1, null], "a": {}}"#).unwrap();
+        assert_eq!(
+            text(&viewer),
+            vec![
+                "{",
+                "  \"b\": [",
+                "    1,",
+                "    null",
+                "  ],",
+                "  \"a\": {}",
+                "}"
+            ]
+        );
+
+        viewer.move_cursor(2);
+        assert_eq!(
+            viewer.path(),
+            [Segment::Key("b".to_string()), Segment::Index(0)]
+        );
+
+        // Toggling a scalar collapses the array holding it.
+        viewer.toggle();
+        assert_eq!(viewer.cursor, 1);
+        assert_eq!(text(&viewer)[1], "  \"b\": […] 2 items,");
+        viewer.toggle();
+        assert_eq!(viewer.lines().len(), 7);
+
+        // Numbers are shown as written.
+        let viewer = Viewer::parse("[12345678901234567890123, 1.50, -0.0]").unwrap();
+        assert_eq!(
+            text(&viewer)[1..4],
+            ["  12345678901234567890123,", "  1.50,", "  -0.0"]
+        );
+        let viewer = Viewer::parse(r#"{"a\"]": [{"b": 1e2}], "c": 1.0, "c": 2.50}"#).unwrap();
+        assert_eq!(
+            text(&viewer),
+            [
+                "{",
+                "  \"a\\\"]\": [",
+                "    {",
+                "      \"b\": 1e2",
+                "    }",
+                "  ],",
+                "  \"c\": 2.50",
+                "}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_expr() {
+        let path = [
+            Segment::Key("items".to_string()),
+            Segment::Index(2),
+            Segment::Key("it's".to_string()),
+        ];
+        assert_eq!(json_path(&path), "$.items[2].\"it's\"");
+        assert_eq!(
+            extract_expr("data", &path),
+            "json_extract(\"data\", '$.items[2].\"it''s\"')"
+        );
+        assert_eq!(extract_expr("my col", &[]), "json_extract(\"my col\", '$')");
+        assert_eq!(extract_expr("order", &[]), "json_extract(\"order\", '$')");
+        assert_eq!(
+            extract_expr("a\"b", &[Segment::Key("say \"hi\"".to_string())]),
+            "json_extract(\"a\"\"b\", '$.say \"hi\"')"
+        );
+    }
+}
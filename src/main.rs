@@ -1,52 +1,431 @@
-use self::config::select_database;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Parser;
 use ratatui::{
     crossterm::{
         cursor::SetCursorStyle,
-        event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+        event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
         execute,
     },
     layout::{Constraint, Direction, Flex, Layout, Rect},
     style::{Color, Style, Stylize},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, Tabs, Wrap},
+    widgets::{
+        Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table, TableState,
+        Tabs, Wrap,
+    },
     DefaultTerminal, Frame,
 };
 use std::{
+    cell::{Cell as StdCell, RefCell},
+    collections::HashMap,
     fmt::Display,
+    io::{self, Write},
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
+mod blob;
+mod cli;
 mod config;
 mod db;
+mod json;
+mod params;
+mod sql;
+mod token;
 
 struct Tab {
+    id: usize,
     name: String,
     input: String,
     char_index: usize,
     query_result: QueryResult,
+    connection: usize,
+    /// Request id of the query whose result the tab is waiting for.
+    pending: Option<u64>,
+    /// Request id of the last query sent.
+    request: u64,
+    /// More rows of the result are being fetched.
+    loading: bool,
+    /// Overrides the query timeout of the connection.
+    timeout: Option<Duration>,
+    /// Statement of a script whose result is shown.
+    statement: usize,
+    /// Byte offset of the submitted SQL in the input.
+    query_offset: usize,
+    /// The input was edited since the query was sent, so the ranges of its
+    /// statements no longer point into it.
+    edited: bool,
+    /// How long the last query took.
+    elapsed: Option<Duration>,
+    /// Where the visual selection started.
+    anchor: usize,
+    /// Last values given to each placeholder.
+    params: HashMap<String, params::Param>,
+    /// Selected cell and scroll position of the results, the scroll position
+    /// being kept up to date while rendering.
+    table_state: RefCell<TableState>,
+    /// First result column shown, for results wider than the pane.
+    column_offset: StdCell<usize>,
+    /// Result column widths set by hand, by column index.
+    column_widths: HashMap<usize, u16>,
+    /// Size result columns to their whole content instead of capping them.
+    fit_columns: bool,
+    /// Show the declared type of the result columns in their header.
+    show_types: bool,
 }
 
+/// Timeouts cycled through with `T`, in seconds.
+const TAB_TIMEOUTS: [u64; 4] = [5, 30, 60, 300];
+
 impl Tab {
-    fn new(name: String) -> Self {
+    fn new(id: usize, name: String, connection: usize) -> Self {
         Self {
+            id,
             name,
             input: String::new(),
             char_index: 0,
             query_result: QueryResult::default(),
+            connection,
+            pending: None,
+            request: 0,
+            loading: false,
+            timeout: None,
+            statement: 0,
+            query_offset: 0,
+            edited: false,
+            anchor: 0,
+            elapsed: None,
+            params: HashMap::new(),
+            table_state: RefCell::default(),
+            column_offset: StdCell::default(),
+            column_widths: HashMap::new(),
+            fit_columns: false,
+            show_types: false,
+        }
+    }
+
+    /// Selects the first cell of the results and forgets the column widths
+    /// set for the previous ones.
+    fn reset_selection(&mut self) {
+        *self.table_state.get_mut() = TableState::default().with_selected_cell((0, 0));
+        self.column_offset.set(0);
+        self.column_widths.clear();
+    }
+
+    /// Widths of the result columns shown.
+    fn result_widths(&self, table: &db::Table) -> Vec<u16> {
+        let mut widths = column_widths(table, self.fit_columns, self.show_types);
+        for (&i, &width) in &self.column_widths {
+            if let Some(w) = widths.get_mut(i) {
+                *w = width;
+            }
+        }
+        widths
+    }
+
+    /// Grows or shrinks the selected result column.
+    fn resize_column(&mut self, delta: i16) {
+        let QueryResult::Table(table) = self.shown_result() else {
+            return;
+        };
+        let column = self.table_state.borrow().selected_column().unwrap_or(0);
+        let Some(&width) = self.result_widths(table).get(column) else {
+            return;
+        };
+        let width = width.saturating_add_signed(delta).max(MIN_COLUMN_WIDTH);
+        self.column_widths.insert(column, width);
+    }
+
+    fn toggle_fit_columns(&mut self) {
+        self.fit_columns = !self.fit_columns;
+        self.column_widths.clear();
+    }
+
+    /// Moves the selected cell of the results, staying within the table.
+    fn move_cell(&mut self, rows: isize, columns: isize) {
+        let QueryResult::Table(table) = self.shown_result() else {
+            return;
+        };
+        let (row_count, column_count) = (table.rows.len(), table.columns.len());
+
+        let state = self.table_state.get_mut();
+        let (row, column) = state.selected_cell().unwrap_or_default();
+        let row = row
+            .saturating_add_signed(rows)
+            .min(row_count.saturating_sub(1));
+        let column = column
+            .saturating_add_signed(columns)
+            .min(column_count.saturating_sub(1));
+        state.select_cell(Some((row, column)));
+    }
+
+    /// The result table with the row and column of the selected cell.
+    fn selected_cell(&self) -> Option<(&db::Table, usize, usize)> {
+        let QueryResult::Table(table) = self.shown_result() else {
+            return None;
+        };
+        let (row, column) = self.table_state.borrow().selected_cell()?;
+        (row < table.rows.len() && column < table.columns.len()).then_some((table, row, column))
+    }
+
+    /// Whether the last loaded row of the results is selected.
+    fn at_last_row(&self) -> bool {
+        let QueryResult::Table(table) = self.shown_result() else {
+            return false;
+        };
+        self.table_state.borrow().selected() == Some(table.rows.len().saturating_sub(1))
+    }
+
+    /// Byte range of the visual selection, including the character under the
+    /// cursor.
+    fn selection(&self) -> Range<usize> {
+        let start = byte_index(&self.input, self.anchor.min(self.char_index));
+        let last = byte_index(&self.input, self.anchor.max(self.char_index));
+        let end = self.input[last..]
+            .chars()
+            .next()
+            .map_or(last, |c| last + c.len_utf8());
+        start..end
+    }
+
+    /// The result to show, picking the selected statement of a script.
+    fn shown_result(&self) -> &QueryResult {
+        match &self.query_result {
+            QueryResult::Script(statements) => statements
+                .get(self.statement)
+                .map_or(&QueryResult::None, |s| &s.result),
+            result => result,
+        }
+    }
+
+    /// Duration of the shown result, per statement for scripts.
+    fn shown_elapsed(&self) -> Option<Duration> {
+        match &self.query_result {
+            QueryResult::Script(statements) => statements.get(self.statement).map(|s| s.elapsed),
+            _ => self.elapsed,
+        }
+    }
+
+    /// Range of the statement that made the script stop, as long as the
+    /// input is the one submitted.
+    fn failed_statement(&self) -> Option<Range<usize>> {
+        let QueryResult::Script(statements) = &self.query_result else {
+            return None;
+        };
+        if self.edited {
+            return None;
         }
+        statements
+            .last()
+            .filter(|s| matches!(s.result, QueryResult::Error(_)))
+            .map(|s| s.range.start + self.query_offset..s.range.end + self.query_offset)
+            .filter(|range| self.input.get(range.clone()).is_some())
+    }
+
+    fn select_statement(&mut self, forward: bool) {
+        let QueryResult::Script(statements) = &self.query_result else {
+            return;
+        };
+        self.statement = if forward {
+            (self.statement + 1).min(statements.len().saturating_sub(1))
+        } else {
+            self.statement.saturating_sub(1)
+        };
+        self.reset_selection();
+    }
+
+    /// Cycles through the timeout presets, then back to the connection's.
+    fn cycle_timeout(&mut self) {
+        let current = self.timeout.map(|t| t.as_secs());
+        self.timeout = match current {
+            None => Some(TAB_TIMEOUTS[0]),
+            Some(secs) => TAB_TIMEOUTS.iter().copied().find(|&t| t > secs),
+        }
+        .map(Duration::from_secs);
     }
 }
 
 struct App {
-    url: String,
     input_mode: InputMode,
     action_sender: mpsc::UnboundedSender<Action>,
-    res_recv: mpsc::UnboundedReceiver<QueryResult>,
+    res_recv: mpsc::UnboundedReceiver<Response>,
     tabs: Vec<Tab>,
     selected_tab: usize,
+    next_tab_id: usize,
+    next_request_id: u64,
     show_help: bool,
+    /// Result rows visible at once, as of the last render.
+    results_height: StdCell<usize>,
+    /// `g` was pressed in the results, waiting for a second one.
+    pending_g: bool,
+    connections: Vec<Connection>,
+    next_connection_id: usize,
+    switcher: Option<Switcher>,
+    param_form: Option<ParamForm>,
+    inspector: Option<Inspector>,
+    /// Quitting was asked while a transaction is open.
+    confirm_quit: bool,
+    /// `--timeout`, taking precedence over the one of every profile.
+    timeout_secs: Option<u64>,
+}
+
+/// An open database connection, shared by the tabs bound to it.
+struct Connection {
+    id: usize,
+    profile: config::Profile,
+    target: db::Target,
+    replica: Option<ReplicaState>,
+    read_only: bool,
+    color: Option<Color>,
+    timeout: Option<Duration>,
+    in_transaction: bool,
+}
+
+impl Connection {
+    fn new(
+        id: usize,
+        profile: config::Profile,
+        target: db::Target,
+        sync_interval: Option<Duration>,
+    ) -> Self {
+        let replica = matches!(target, db::Target::Replica { .. }).then(|| ReplicaState {
+            interval: sync_interval,
+            ..Default::default()
+        });
+        let read_only = profile.read_only
+            || target
+                .token()
+                .and_then(token::Claims::decode)
+                .is_some_and(|claims| claims.is_read_only());
+        let color = profile.color.as_deref().and_then(|c| c.parse().ok());
+        let timeout = profile.timeout_secs.map(Duration::from_secs);
+        Self {
+            id,
+            profile,
+            target,
+            replica,
+            read_only,
+            color,
+            timeout,
+            in_transaction: false,
+        }
+    }
+}
+
+/// Popup listing the databases that can be switched to.
+#[derive(Default)]
+struct Switcher {
+    entries: Vec<config::Entry>,
+    selected: usize,
+    /// Id and profile of the connection being opened.
+    connecting: Option<(usize, config::Profile)>,
+    /// The token command of the profile connecting is still running.
+    resolving: bool,
+    error: Option<String>,
+}
+
+impl Switcher {
+    /// Lists the open connections followed by the saved profiles and the
+    /// databases from the Turso CLI cache that aren't open yet.
+    fn load(connections: &[Connection]) -> Self {
+        let mut entries = connections
+            .iter()
+            .map(|c| config::Entry {
+                name: c.profile.name.clone(),
+                profile: Ok(c.profile.clone()),
+            })
+            .collect::<Vec<_>>();
+
+        let mut error = None;
+        match config::load_entries() {
+            Ok(loaded) => entries.extend(loaded.into_iter().filter(|e| {
+                !connections
+                    .iter()
+                    .any(|c| Some(c.profile.location()) == e.location())
+            })),
+            Err(err) => error = Some(err.to_string()),
+        }
+
+        Self {
+            entries,
+            error,
+            ..Default::default()
+        }
+    }
+}
+
+/// Popup showing the selected cell, or every column of the selected row, in
+/// full.
+#[derive(Debug, Default)]
+struct Inspector {
+    row: bool,
+    /// First line shown, past the 65535 lines a paragraph scrolls for the
+    /// hex dump of large blobs.
+    scroll: usize,
+    /// Tree of the selected cell when it holds a JSON object or array.
+    json: Option<json::Viewer>,
+    /// First line of the JSON tree shown, as of the last render.
+    json_offset: StdCell<usize>,
+    /// Outcome of the last copy.
+    status: Option<String>,
+    /// What the selected blob holds, detected once when the inspector opens.
+    content: Option<blob::Content>,
+    /// Dump a blob as hex even when its content could be decoded.
+    hex: bool,
+    /// Lines shown at once, as of the last render.
+    height: StdCell<u16>,
+    /// Width the lines wrap at, as of the last render.
+    width: StdCell<u16>,
+}
+
+/// Popup asking for the values of the placeholders of a query.
+struct ParamForm {
+    sql: String,
+    offset: usize,
+    fields: Vec<(String, params::Param)>,
+    selected: usize,
+    error: Option<String>,
+}
+
+/// Sync bookkeeping for an embedded replica connection.
+#[derive(Default)]
+struct ReplicaState {
+    interval: Option<Duration>,
+    syncing: bool,
+    last_sync: Option<(Instant, db::SyncInfo)>,
+    /// When the last sync ended, whether it succeeded or not.
+    last_attempt: Option<Instant>,
+    /// Syncs failed in a row.
+    failures: u32,
+    /// Error of the last sync when it failed.
+    error: Option<String>,
+}
+
+/// Longest wait between syncs retried after failures.
+const MAX_SYNC_BACKOFF: Duration = Duration::from_secs(300);
+
+impl ReplicaState {
+    /// Wait before the next periodic sync, doubling with each failure.
+    fn backoff(&self, interval: Duration) -> Duration {
+        let backoff = interval.saturating_mul(1 << self.failures.min(16));
+        backoff.min(MAX_SYNC_BACKOFF.max(interval))
+    }
+
+    fn sync_due(&self) -> bool {
+        let Some(interval) = self.interval else {
+            return false;
+        };
+        !self.syncing
+            && self
+                .last_attempt
+                .is_none_or(|at| at.elapsed() >= self.backoff(interval))
+    }
 }
 
 impl App {
@@ -58,8 +437,7 @@ impl App {
 
         loop {
             while let Ok(res) = self.res_recv.try_recv() {
-                let selected_tab = &mut self.tabs[self.selected_tab];
-                selected_tab.query_result = res;
+                self.handle_response(res);
             }
             terminal.draw(|f| self.draw(f))?;
 
@@ -67,17 +445,46 @@ impl App {
             if event::poll(timeout)? {
                 if let Event::Key(key) = event::read()? {
                     match self.input_mode {
+                        _ if self.confirm_quit => match key.code {
+                            KeyCode::Char('y' | 'q') => return Ok(()),
+                            _ => self.confirm_quit = false,
+                        },
+                        _ if self.param_form.is_some() => self.handle_param_key(key),
+                        _ if self.inspector.is_some() => self.handle_inspector_key(key),
+                        _ if self.switcher.is_some() => self.handle_switcher_key(key),
                         InputMode::Normal => match (key.modifiers, key.code) {
                             (KeyModifiers::CONTROL, KeyCode::Char('n')) => self.new_tab(),
                             (KeyModifiers::CONTROL, KeyCode::Char('w')) => self.delete_tab(),
                             (KeyModifiers::CONTROL, KeyCode::Char('r')) => self.submit_query(),
+                            (KeyModifiers::CONTROL, KeyCode::Char('e')) => self.submit_statement(),
+                            (KeyModifiers::CONTROL, KeyCode::Char('c')) => self.cancel_query(),
                             (KeyModifiers::CONTROL, KeyCode::Char('t')) => self.get_tables(),
+                            (KeyModifiers::CONTROL, KeyCode::Char('s')) => self.sync(),
+                            (KeyModifiers::CONTROL, KeyCode::Char('o')) => {
+                                self.switcher = Some(Switcher::load(&self.connections));
+                            }
                             (_, KeyCode::Char('H')) => self.previous_tab(),
                             (_, KeyCode::Char('L')) => self.next_tab(),
+                            (_, KeyCode::Char('[')) => {
+                                self.tabs[self.selected_tab].select_statement(false);
+                            }
+                            (_, KeyCode::Char(']')) => {
+                                self.tabs[self.selected_tab].select_statement(true);
+                            }
+                            (_, KeyCode::Char('M')) => self.load_more(),
+                            (_, KeyCode::Tab) => self.input_mode = InputMode::Results,
+                            (_, KeyCode::Char('T')) => {
+                                self.tabs[self.selected_tab].cycle_timeout();
+                            }
                             (_, KeyCode::Char('i')) => {
                                 self.input_mode = InputMode::Insert;
                                 self.update_cursor_shape()?;
                             }
+                            (_, KeyCode::Char('v')) => {
+                                self.input_mode = InputMode::Visual;
+                                let selected_tab = &mut self.tabs[self.selected_tab];
+                                selected_tab.anchor = selected_tab.char_index;
+                            }
                             (_, KeyCode::Char('?')) => {
                                 self.show_help = !self.show_help;
                             }
@@ -103,8 +510,14 @@ impl App {
                                 }
                             }
                             (_, KeyCode::Char('q')) => {
-                                return Ok(());
+                                if !self.connections.iter().any(|c| c.in_transaction) {
+                                    return Ok(());
+                                }
+                                self.confirm_quit = true;
                             }
+                            (_, KeyCode::Char('B')) => self.transaction(TransactionOp::Begin),
+                            (_, KeyCode::Char('C')) => self.transaction(TransactionOp::Commit),
+                            (_, KeyCode::Char('R')) => self.transaction(TransactionOp::Rollback),
                             (_, KeyCode::Char('0')) => {
                                 let selected_tab = &mut self.tabs[self.selected_tab];
                                 selected_tab.char_index = 0;
@@ -139,15 +552,470 @@ impl App {
                             _ => {}
                         },
                         InputMode::Insert => {}
+                        InputMode::Results => self.handle_results_key(key),
+                        InputMode::Visual => match (key.modifiers, key.code) {
+                            (KeyModifiers::CONTROL, KeyCode::Char('r' | 'e')) => {
+                                self.submit_selection();
+                            }
+                            (_, KeyCode::Esc | KeyCode::Char('v')) => {
+                                self.input_mode = InputMode::Normal;
+                            }
+                            (_, KeyCode::Char('b')) => self.move_last(),
+                            (_, KeyCode::Char('w')) => self.move_next(),
+                            (_, KeyCode::Char('0')) => {
+                                let selected_tab = &mut self.tabs[self.selected_tab];
+                                selected_tab.char_index = 0;
+                            }
+                            (_, KeyCode::Char('$')) => {
+                                let selected_tab = &mut self.tabs[self.selected_tab];
+                                selected_tab.char_index =
+                                    selected_tab.input.len().saturating_sub(1);
+                            }
+                            (_, KeyCode::Left | KeyCode::Char('h')) => self.move_cursor_left(),
+                            (_, KeyCode::Right | KeyCode::Char('l')) => self.move_cursor_right(),
+                            _ => {}
+                        },
                     }
                 }
             }
             if last_tick.elapsed() >= tick_rate {
                 last_tick = Instant::now();
+
+                let due = self
+                    .connections
+                    .iter()
+                    .filter(|c| c.replica.as_ref().is_some_and(ReplicaState::sync_due))
+                    .map(|c| c.id)
+                    .collect::<Vec<_>>();
+                for id in due {
+                    self.sync_connection(id);
+                }
+            }
+        }
+    }
+
+    fn handle_response(&mut self, res: Response) {
+        match res {
+            Response::Query {
+                tab,
+                request,
+                result,
+                elapsed,
+            } => {
+                // The tab may have been closed, or sent a newer query since.
+                let Some(tab) = self
+                    .tabs
+                    .iter_mut()
+                    .find(|t| t.id == tab && t.pending == Some(request))
+                else {
+                    return;
+                };
+                // Show the last statement of a script, the failing one if any.
+                if let QueryResult::Script(statements) = &result {
+                    tab.statement = statements.len().saturating_sub(1);
+                }
+                tab.query_result = result;
+                tab.elapsed = Some(elapsed);
+                tab.pending = None;
+                tab.reset_selection();
+            }
+            Response::TokenResolved { id } => {
+                if let Some(switcher) = self.connecting_switcher(id) {
+                    switcher.resolving = false;
+                }
+            }
+            Response::Connected { id, res } => {
+                // The connection may have been abandoned since.
+                let Some(switcher) = self.connecting_switcher(id) else {
+                    return;
+                };
+                match res {
+                    Ok(target) => {
+                        let Some((_, mut profile)) =
+                            self.switcher.take().and_then(|s| s.connecting)
+                        else {
+                            return;
+                        };
+                        profile.timeout_secs = self.timeout_secs.or(profile.timeout_secs);
+                        self.connections
+                            .push(Connection::new(id, profile, target, None));
+                        self.tabs[self.selected_tab].connection = id;
+                    }
+                    Err(err) => {
+                        switcher.connecting = None;
+                        switcher.resolving = false;
+                        switcher.error = Some(err);
+                    }
+                }
+            }
+            Response::Rows { tab, request, rows } => {
+                let Some(tab) = self
+                    .tabs
+                    .iter_mut()
+                    .find(|t| t.id == tab && t.request == request)
+                else {
+                    return;
+                };
+                tab.loading = false;
+                let QueryResult::Table(table) = &mut tab.query_result else {
+                    return;
+                };
+                match rows {
+                    Ok((rows, more)) => {
+                        table.extend(rows);
+                        table.more = more;
+                    }
+                    Err(err) => {
+                        table.more = false;
+                        tab.query_result = QueryResult::Error(err);
+                    }
+                }
+            }
+            Response::Transaction {
+                id,
+                tab,
+                open,
+                error,
+            } => {
+                if let Some(connection) = self.connections.iter_mut().find(|c| c.id == id) {
+                    connection.in_transaction = open;
+                }
+                if let (Some(err), Some(tab)) = (error, self.tabs.iter_mut().find(|t| t.id == tab))
+                {
+                    tab.query_result = QueryResult::Error(err);
+                }
+            }
+            Response::Synced { id, res } => {
+                let Some(replica) = self
+                    .connections
+                    .iter_mut()
+                    .find(|c| c.id == id)
+                    .and_then(|c| c.replica.as_mut())
+                else {
+                    return;
+                };
+                replica.syncing = false;
+                replica.last_attempt = Some(Instant::now());
+                match res {
+                    Ok(info) => {
+                        replica.last_sync = Some((Instant::now(), info));
+                        replica.failures = 0;
+                        replica.error = None;
+                    }
+                    Err(err) => {
+                        replica.failures += 1;
+                        replica.error = Some(err);
+                    }
+                }
             }
         }
     }
 
+    fn handle_results_key(&mut self, key: KeyEvent) {
+        let half_page = (self.results_height.get() / 2).max(1) as isize;
+        let pending_g = std::mem::take(&mut self.pending_g);
+
+        match (key.modifiers, key.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char('d')) => self.move_results(half_page, 0),
+            (KeyModifiers::CONTROL, KeyCode::Char('u')) => self.move_results(-half_page, 0),
+            (KeyModifiers::CONTROL, KeyCode::Char('c')) => self.cancel_query(),
+            (_, KeyCode::Esc | KeyCode::Tab | KeyCode::Char('q')) => {
+                self.input_mode = InputMode::Normal;
+            }
+            (_, KeyCode::Down | KeyCode::Char('j')) => self.move_results(1, 0),
+            (_, KeyCode::Up | KeyCode::Char('k')) => self.move_results(-1, 0),
+            (_, KeyCode::Left | KeyCode::Char('h')) => self.move_results(0, -1),
+            (_, KeyCode::Right | KeyCode::Char('l')) => self.move_results(0, 1),
+            (_, KeyCode::Char('0')) => self.move_results(0, isize::MIN),
+            (_, KeyCode::Char('$')) => self.move_results(0, isize::MAX),
+            (_, KeyCode::Char('g')) if pending_g => self.move_results(isize::MIN, 0),
+            (_, KeyCode::Char('g')) => self.pending_g = true,
+            (_, KeyCode::Char('G')) => self.move_results(isize::MAX, 0),
+            (_, KeyCode::Enter) => self.inspect(false),
+            (_, KeyCode::Char('r')) => self.inspect(true),
+            (_, KeyCode::Char('M')) => self.load_more(),
+            (_, KeyCode::Char('<')) => self.tabs[self.selected_tab].resize_column(-2),
+            (_, KeyCode::Char('>')) => self.tabs[self.selected_tab].resize_column(2),
+            (_, KeyCode::Char('=')) => self.tabs[self.selected_tab].toggle_fit_columns(),
+            (_, KeyCode::Char('t')) => {
+                let selected_tab = &mut self.tabs[self.selected_tab];
+                selected_tab.show_types = !selected_tab.show_types;
+            }
+            (_, KeyCode::Char('[')) => self.tabs[self.selected_tab].select_statement(false),
+            (_, KeyCode::Char(']')) => self.tabs[self.selected_tab].select_statement(true),
+            _ => {}
+        }
+    }
+
+    fn inspect(&mut self, row: bool) {
+        let Some((table, r, c)) = self.tabs[self.selected_tab].selected_cell() else {
+            return;
+        };
+        let (json, content) = match table.rows[r][c].value() {
+            libsql::Value::Text(text) if !row => (json::Viewer::parse(text), None),
+            libsql::Value::Blob(bytes) if !row => (None, Some(blob::detect(bytes))),
+            _ => (None, None),
+        };
+        self.inspector = Some(Inspector {
+            row,
+            json,
+            content,
+            ..Inspector::default()
+        });
+    }
+
+    fn handle_inspector_key(&mut self, key: KeyEvent) {
+        let Some(inspector) = self.inspector.as_ref() else {
+            return;
+        };
+        let lines = match self.dumped_blob(inspector) {
+            Some(bytes) => blob::dump_lines(bytes.len()),
+            // Long lines wrap, and paragraphs only scroll that far.
+            None => self
+                .inspector_text(inspector)
+                .map_or(0, |(_, text)| {
+                    Paragraph::new(text)
+                        .wrap(Wrap { trim: false })
+                        .line_count(inspector.width.get().max(1))
+                })
+                .min(u16::MAX as usize),
+        };
+        let last_line = lines.saturating_sub(1);
+        let page = inspector.height.get().max(1) as usize;
+        let Some(inspector) = self.inspector.as_mut() else {
+            return;
+        };
+
+        if let Some(viewer) = inspector.json.as_mut() {
+            inspector.status = None;
+            match (key.modifiers, key.code) {
+                (KeyModifiers::CONTROL, KeyCode::Char('d')) => viewer.move_cursor(10),
+                (KeyModifiers::CONTROL, KeyCode::Char('u')) => viewer.move_cursor(-10),
+                (_, KeyCode::Esc | KeyCode::Char('q')) => self.inspector = None,
+                (_, KeyCode::Char('r')) => self.inspect(true),
+                (_, KeyCode::Down | KeyCode::Char('j')) => viewer.move_cursor(1),
+                (_, KeyCode::Up | KeyCode::Char('k')) => viewer.move_cursor(-1),
+                (_, KeyCode::Char('g')) => viewer.move_cursor(isize::MIN),
+                (_, KeyCode::Char('G')) => viewer.move_cursor(isize::MAX),
+                (_, KeyCode::Enter | KeyCode::Char(' ')) => viewer.toggle(),
+                (_, KeyCode::Char('y')) => {
+                    let Some((table, _, column)) = self.tabs[self.selected_tab].selected_cell()
+                    else {
+                        return;
+                    };
+                    let expr = json::extract_expr(&table.columns[column], viewer.path());
+                    inspector.status = Some(match copy_to_clipboard(&expr) {
+                        Ok(()) => format!("Copied {expr}"),
+                        Err(err) => format!("Copy failed: {err}"),
+                    });
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        inspector.scroll = match (key.modifiers, key.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char('d')) => inspector.scroll.saturating_add(10),
+            (KeyModifiers::CONTROL, KeyCode::Char('u')) => inspector.scroll.saturating_sub(10),
+            (_, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q')) => {
+                self.inspector = None;
+                return;
+            }
+            (_, KeyCode::Char('r')) => {
+                let row = inspector.row;
+                self.inspect(!row);
+                return;
+            }
+            (_, KeyCode::Char('x')) => {
+                inspector.hex = !inspector.hex;
+                0
+            }
+            (_, KeyCode::PageDown) => inspector.scroll.saturating_add(page),
+            (_, KeyCode::PageUp) => inspector.scroll.saturating_sub(page),
+            (_, KeyCode::Down | KeyCode::Char('j')) => inspector.scroll.saturating_add(1),
+            (_, KeyCode::Up | KeyCode::Char('k')) => inspector.scroll.saturating_sub(1),
+            (_, KeyCode::Char('g')) => 0,
+            (_, KeyCode::Char('G')) => last_line,
+            _ => inspector.scroll,
+        }
+        .min(last_line);
+    }
+
+    fn move_results(&mut self, rows: isize, columns: isize) {
+        let selected_tab = &mut self.tabs[self.selected_tab];
+        selected_tab.move_cell(rows, columns);
+        // Reaching the last loaded row fetches the next page.
+        if rows > 0 && selected_tab.at_last_row() {
+            self.load_more();
+        }
+    }
+
+    fn handle_param_key(&mut self, key: KeyEvent) {
+        let Some(form) = self.param_form.as_mut() else {
+            return;
+        };
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        let last = form.fields.len().saturating_sub(1);
+        let field = &mut form.fields[form.selected].1;
+
+        match key.code {
+            KeyCode::Esc => self.param_form = None,
+            KeyCode::Tab | KeyCode::Down if form.selected < last => {
+                form.selected += 1;
+            }
+            KeyCode::BackTab | KeyCode::Up => form.selected = form.selected.saturating_sub(1),
+            KeyCode::Left => field.kind = field.kind.cycle(false),
+            KeyCode::Right => field.kind = field.kind.cycle(true),
+            KeyCode::Backspace => {
+                field.value.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                field.value.push(c)
+            }
+            KeyCode::Enter => {
+                let mut values = HashMap::new();
+                for (i, (name, param)) in form.fields.iter().enumerate() {
+                    match param.to_value() {
+                        Ok(value) => values.insert(name.clone(), value),
+                        Err(err) => {
+                            form.selected = i;
+                            form.error = Some(format!("{name}: {err}"));
+                            return;
+                        }
+                    };
+                }
+
+                let Some(form) = self.param_form.take() else {
+                    return;
+                };
+                self.tabs[self.selected_tab].params.extend(form.fields);
+                self.send_query(form.sql, values);
+                self.tabs[self.selected_tab].query_offset = form.offset;
+            }
+            _ => {}
+        }
+    }
+
+    /// The switcher, when it's opening the connection `id`.
+    fn connecting_switcher(&mut self, id: usize) -> Option<&mut Switcher> {
+        self.switcher
+            .as_mut()
+            .filter(|s| s.connecting.as_ref().is_some_and(|(c, _)| *c == id))
+    }
+
+    fn handle_switcher_key(&mut self, key: KeyEvent) {
+        let Some(switcher) = self.switcher.as_mut() else {
+            return;
+        };
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        if let Some((id, _)) = &switcher.connecting {
+            // Only closing is left while connecting, abandoning the connection.
+            if matches!(key.code, KeyCode::Esc | KeyCode::Char('q')) {
+                let _ = self.action_sender.send(Action::Abandon { id: *id });
+                self.switcher = None;
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.switcher = None,
+            KeyCode::Down | KeyCode::Char('j')
+                if switcher.selected + 1 < switcher.entries.len() =>
+            {
+                switcher.selected += 1;
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                switcher.selected = switcher.selected.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                let Some(entry) = switcher.entries.get(switcher.selected) else {
+                    return;
+                };
+                let profile = match &entry.profile {
+                    Ok(profile) => profile.clone(),
+                    Err(err) => {
+                        switcher.error = Some(err.clone());
+                        return;
+                    }
+                };
+
+                if let Some(connection) = self
+                    .connections
+                    .iter()
+                    .find(|c| c.profile.location() == profile.location())
+                {
+                    self.tabs[self.selected_tab].connection = connection.id;
+                    self.switcher = None;
+                    return;
+                }
+
+                let id = self.next_connection_id;
+                self.next_connection_id += 1;
+                let _ = self.action_sender.send(Action::Connect {
+                    id,
+                    profile: profile.clone(),
+                });
+                switcher.resolving = profile.runs_token_command();
+                switcher.connecting = Some((id, profile));
+                switcher.error = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn active_connection(&self) -> Option<&Connection> {
+        let selected_tab = self.tabs.get(self.selected_tab)?;
+        self.connections
+            .iter()
+            .find(|c| c.id == selected_tab.connection)
+    }
+
+    fn transaction(&mut self, op: TransactionOp) {
+        let Some(connection) = self.active_connection() else {
+            return;
+        };
+        if connection.in_transaction == (op == TransactionOp::Begin) {
+            let selected_tab = &mut self.tabs[self.selected_tab];
+            selected_tab.query_result = QueryResult::Error(match op {
+                TransactionOp::Begin => "A transaction is already open".to_string(),
+                _ => "No transaction is open".to_string(),
+            });
+            return;
+        }
+
+        let _ = self.action_sender.send(Action::Transaction {
+            id: connection.id,
+            tab: self.tabs[self.selected_tab].id,
+            op,
+        });
+    }
+
+    fn sync(&mut self) {
+        if let Some(selected_tab) = self.tabs.get(self.selected_tab) {
+            self.sync_connection(selected_tab.connection);
+        }
+    }
+
+    fn sync_connection(&mut self, id: usize) {
+        let Some(replica) = self
+            .connections
+            .iter_mut()
+            .find(|c| c.id == id)
+            .and_then(|c| c.replica.as_mut())
+        else {
+            return;
+        };
+        if replica.syncing {
+            return;
+        }
+        replica.syncing = true;
+        let _ = self.action_sender.send(Action::Sync(id));
+    }
+
     fn is_word_char(c: char) -> bool {
         c.is_alphanumeric() || c == '_'
     }
@@ -235,11 +1103,12 @@ impl App {
         selected_tab.char_index = idx;
     }
 
-    fn get_tables(&self) {
-        let _ = self.action_sender.send(Action::Query(
+    fn get_tables(&mut self) {
+        self.send_query(
             "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'"
                 .to_string(),
-        ));
+            HashMap::new(),
+        );
     }
     fn submit_query(&mut self) {
         let selected_tab = &self.tabs[self.selected_tab];
@@ -248,14 +1117,118 @@ impl App {
             return;
         }
 
-        let _ = self
-            .action_sender
-            .send(Action::Query(selected_tab.input.clone()));
+        self.submit_range(0..selected_tab.input.len());
+    }
+
+    /// Runs the statement under the cursor.
+    fn submit_statement(&mut self) {
+        let selected_tab = &self.tabs[self.selected_tab];
+        let cursor = byte_index(&selected_tab.input, selected_tab.char_index);
+        if let Some(range) = sql::statement_at(&selected_tab.input, cursor) {
+            self.submit_range(range);
+        }
+    }
+
+    fn submit_selection(&mut self) {
+        self.input_mode = InputMode::Normal;
+        let range = self.tabs[self.selected_tab].selection();
+        self.submit_range(range);
+    }
+
+    /// Runs part of the input, remembering where it starts so script
+    /// statements can be located in the editor.
+    fn submit_range(&mut self, range: Range<usize>) {
+        let selected_tab = &self.tabs[self.selected_tab];
+        let sql = selected_tab.input[range.clone()].to_string();
+        if sql.trim().is_empty() {
+            return;
+        }
+
+        // Ask for the placeholder values first, starting from the last ones.
+        let names = params::names(&sql);
+        if !names.is_empty() {
+            let fields = names
+                .into_iter()
+                .map(|name| {
+                    let param = selected_tab.params.get(&name).cloned().unwrap_or_default();
+                    (name, param)
+                })
+                .collect();
+            self.param_form = Some(ParamForm {
+                sql,
+                offset: range.start,
+                fields,
+                selected: 0,
+                error: None,
+            });
+            return;
+        }
+
+        self.send_query(sql, HashMap::new());
+        self.tabs[self.selected_tab].query_offset = range.start;
+    }
+
+    fn send_query(&mut self, sql: String, params: HashMap<String, libsql::Value>) {
+        let request = self.next_request_id;
+        self.next_request_id += 1;
+
+        let timeout = self.query_timeout();
+        let selected_tab = &mut self.tabs[self.selected_tab];
+        selected_tab.pending = Some(request);
+        selected_tab.request = request;
+        selected_tab.loading = false;
+        selected_tab.query_offset = 0;
+        selected_tab.edited = false;
+
+        let _ = self.action_sender.send(Action::Query {
+            tab: selected_tab.id,
+            request,
+            connection: selected_tab.connection,
+            sql,
+            params,
+            timeout,
+        });
+    }
+
+    /// Timeout of the current tab, falling back to the one of its connection.
+    fn query_timeout(&self) -> Option<Duration> {
+        let selected_tab = &self.tabs[self.selected_tab];
+        selected_tab
+            .timeout
+            .or_else(|| self.active_connection().and_then(|c| c.timeout))
+    }
+
+    /// Fetches the next page of rows of the shown result.
+    fn load_more(&mut self) {
+        let selected_tab = &mut self.tabs[self.selected_tab];
+        let more = matches!(&selected_tab.query_result, QueryResult::Table(t) if t.more);
+        if !more || selected_tab.loading || selected_tab.pending.is_some() {
+            return;
+        }
+        selected_tab.loading = true;
+
+        let _ = self.action_sender.send(Action::FetchMore {
+            tab: selected_tab.id,
+        });
+    }
+
+    fn cancel_query(&mut self) {
+        let selected_tab = &mut self.tabs[self.selected_tab];
+        if selected_tab.pending.take().is_none() {
+            return;
+        }
+        selected_tab.query_result = QueryResult::Cancelled;
+
+        let _ = self.action_sender.send(Action::Cancel {
+            tab: selected_tab.id,
+        });
     }
 
     fn update_cursor_shape(&self) -> anyhow::Result<()> {
         let cursor = match self.input_mode {
-            InputMode::Normal => SetCursorStyle::SteadyBlock,
+            InputMode::Normal | InputMode::Visual | InputMode::Results => {
+                SetCursorStyle::SteadyBlock
+            }
             InputMode::Insert => SetCursorStyle::SteadyBar,
         };
         execute!(std::io::stdout(), cursor)?;
@@ -277,7 +1250,9 @@ impl App {
     fn new_tab(&mut self) {
         let tab_number = self.tabs.len() + 1;
         let name = format!("Query {}", tab_number);
-        self.tabs.push(Tab::new(name));
+        let connection = self.tabs.get(self.selected_tab).map_or(0, |t| t.connection);
+        self.tabs.push(Tab::new(self.next_tab_id, name, connection));
+        self.next_tab_id += 1;
         self.selected_tab = self.tabs.len() - 1;
     }
 
@@ -286,11 +1261,11 @@ impl App {
             return;
         }
 
-        self.tabs.remove(self.selected_tab);
+        let tab = self.tabs.remove(self.selected_tab);
+        // Stops the tab's query, and lets the worker forget it.
+        let _ = self.action_sender.send(Action::Cancel { tab: tab.id });
 
-        if self.selected_tab == 0 {
-            self.selected_tab += 1;
-        } else {
+        if self.selected_tab > 0 {
             self.selected_tab -= 1;
         }
 
@@ -306,18 +1281,27 @@ impl App {
     fn clear_results(&mut self) {
         let selected_tab = &mut self.tabs[self.selected_tab];
         selected_tab.query_result = QueryResult::None;
+        selected_tab.elapsed = None;
+        selected_tab.loading = false;
+        selected_tab.reset_selection();
+
+        let _ = self.action_sender.send(Action::Release {
+            tab: selected_tab.id,
+        });
     }
 
     fn delete_input(&mut self) {
         let selected_tab = &mut self.tabs[self.selected_tab];
         selected_tab.input.clear();
         selected_tab.char_index = 0;
+        selected_tab.edited = true;
     }
 
     fn append_char(&mut self, c: char) {
         let selected_tab = &mut self.tabs[self.selected_tab];
         selected_tab.input.insert(selected_tab.char_index, c);
         selected_tab.char_index += 1;
+        selected_tab.edited = true;
     }
 
     fn delete_last_char(&mut self) {
@@ -325,6 +1309,7 @@ impl App {
         if selected_tab.char_index > 0 {
             selected_tab.input.remove(selected_tab.char_index - 1);
             selected_tab.char_index -= 1;
+            selected_tab.edited = true;
         }
     }
 
@@ -333,6 +1318,7 @@ impl App {
 
         if selected_tab.char_index < selected_tab.input.len() {
             selected_tab.input.remove(selected_tab.char_index);
+            selected_tab.edited = true;
 
             if selected_tab.char_index >= selected_tab.input.len() && selected_tab.char_index > 0 {
                 selected_tab.char_index -= 1;
@@ -362,22 +1348,67 @@ impl App {
     }
 
     fn render_top_bar(&self, f: &mut Frame, chunks: Rect) {
+        let connection = self.active_connection();
+        let replica = connection.and_then(|c| c.replica.as_ref());
+
+        let in_transaction = connection.is_some_and(|c| c.in_transaction);
+
+        let mut misc_width = if replica.is_some() { 48 } else { 32 };
+        if in_transaction {
+            misc_width += 17;
+        }
         let top_container = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Length(32), Constraint::Min(0)].as_ref())
+            .constraints([Constraint::Length(misc_width), Constraint::Min(0)].as_ref())
             .split(chunks);
 
         let mode_span = Span::styled(
             self.input_mode.to_string(),
             Style::default().bold().bg(Color::Blue).fg(Color::Black),
         );
-        let misc_line = Line::from(vec![mode_span]);
+        let mut misc_spans = vec![mode_span];
+        if in_transaction {
+            misc_spans.push(Span::raw(" "));
+            misc_spans.push(Span::styled(
+                " IN TRANSACTION ",
+                Style::default().bold().bg(Color::Magenta).fg(Color::Black),
+            ));
+        }
+        if let Some(replica) = replica {
+            misc_spans.push(Span::raw(" "));
+            misc_spans.push(replica_span(replica));
+        }
+        let misc_line = Line::from(misc_spans);
         let misc_block =
             Paragraph::new(misc_line).block(Block::default().borders(Borders::ALL).title(" Misc "));
         f.render_widget(misc_block, top_container[0]);
 
-        let url_block = Paragraph::new(format!("Connected to: {}", self.url))
-            .block(Block::default().borders(Borders::ALL).title(" Database "));
+        let mut url_spans = vec![Span::raw("Connected to: ")];
+        if let Some(connection) = connection {
+            let location = connection.target.to_string();
+            if connection.profile.name != location {
+                url_spans.push(Span::styled(
+                    format!("{} ", connection.profile.name),
+                    Style::default().bold(),
+                ));
+            }
+            url_spans.push(Span::raw(location));
+        }
+        if connection.is_some_and(|c| c.read_only) {
+            url_spans.push(Span::styled(
+                " read-only",
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        let border_style = connection
+            .and_then(|c| c.color)
+            .map_or(Style::default(), |color| Style::default().fg(color));
+        let url_block = Paragraph::new(Line::from(url_spans)).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style)
+                .title(" Database "),
+        );
         f.render_widget(url_block, top_container[1]);
     }
 
@@ -399,12 +1430,32 @@ impl App {
     fn render_query(&self, f: &mut Frame, chunks: Rect) {
         let selected_tab = &self.tabs[self.selected_tab];
 
-        let query_block = Paragraph::new(selected_tab.input.to_string())
-            .block(Block::default().borders(Borders::ALL).title(" SQL "))
+        let mut title = Line::from(" SQL ");
+        if let Some(timeout) = self.query_timeout() {
+            title.push_span(Span::styled(
+                format!("timeout {}s ", timeout.as_secs()),
+                Style::default().fg(Color::Indexed(246)),
+            ));
+        }
+        let input = match selected_tab.failed_statement() {
+            _ if self.input_mode == InputMode::Visual => highlight(
+                &selected_tab.input,
+                selected_tab.selection(),
+                Style::default().reversed(),
+            ),
+            Some(range) => highlight(
+                &selected_tab.input,
+                range,
+                Style::default().fg(Color::Red).underlined(),
+            ),
+            _ => Text::from(selected_tab.input.as_str()),
+        };
+        let query_block = Paragraph::new(input)
+            .block(Block::default().borders(Borders::ALL).title(title))
             .wrap(Wrap { trim: false });
         f.render_widget(query_block, chunks);
 
-        {
+        if self.input_mode != InputMode::Results {
             let input_width = chunks.width - 2;
             let input_lines = wrap_text(&selected_tab.input, input_width);
             let (cursor_x, cursor_y) =
@@ -415,16 +1466,78 @@ impl App {
     }
     fn render_results(&self, f: &mut Frame, chunks: Rect) {
         let selected_tab = &self.tabs[self.selected_tab];
-        let results_block = match &selected_tab.query_result {
-            QueryResult::None => Paragraph::new(" No results")
-                .block(Block::default().borders(Borders::ALL).title(" Results ")),
+        // Which statement of a script is shown, e.g. ` 2/3`.
+        let counter = match &selected_tab.query_result {
+            QueryResult::Script(statements) => {
+                format!(" {}/{}", selected_tab.statement + 1, statements.len())
+            }
+            _ => String::new(),
+        };
+        let mut stats = String::new();
+        if let QueryResult::Table(table) = selected_tab.shown_result() {
+            stats = format!(
+                " · {}{} rows × {} cols",
+                table.rows.len(),
+                if table.more { "+" } else { "" },
+                table.columns.len()
+            );
+            if selected_tab.loading {
+                stats.push_str(" · loading…");
+            } else if table.more && !matches!(selected_tab.query_result, QueryResult::Script(_)) {
+                stats.push_str(" · M to load more");
+            }
+        }
+        if let Some(elapsed) = selected_tab.shown_elapsed() {
+            stats.push_str(&format!(" · {}", format_duration(elapsed)));
+        }
+        let title = format!(" Results{counter}{stats} ");
+        let results_block = match selected_tab.shown_result() {
+            _ if selected_tab.pending.is_some() => Paragraph::new(Text::from(vec![
+                Line::from(" Running query…"),
+                Line::from(" Ctrl-c to cancel").style(Style::default().fg(Color::Indexed(246))),
+            ]))
+            .block(Block::default().borders(Borders::ALL).title(" Results ")),
+            QueryResult::None | QueryResult::Script(_) => Paragraph::new(" No results")
+                .block(Block::default().borders(Borders::ALL).title(title)),
             QueryResult::Table(table) => {
-                let rows = &table.rows;
-                let columns = &table.columns;
-
-                let header_cells = columns
-                    .iter()
-                    .map(|h| Cell::from(Text::from(h.to_uppercase())));
+                let mut state = selected_tab.table_state.borrow_mut();
+                let (row, column) = state.selected_cell().unwrap_or_default();
+
+                // Scroll horizontally so the selected column stays visible.
+                let widths = selected_tab.result_widths(table);
+                let inner_width = chunks.width.saturating_sub(2);
+                let mut first = selected_tab.column_offset.get().min(column);
+                while first < column && span_width(&widths[first..=column]) > inner_width {
+                    first += 1;
+                }
+                let mut last = first;
+                while last < widths.len()
+                    && (last == first || span_width(&widths[first..=last]) <= inner_width)
+                {
+                    last += 1;
+                }
+                selected_tab.column_offset.set(first);
+                let shown = first..last;
+
+                // Only the visible rows are built, results can be large.
+                let height = (chunks.height.saturating_sub(3) as usize).max(1);
+                let mut offset = state.offset().min(row);
+                if row >= offset + height {
+                    offset = row + 1 - height;
+                }
+                *state.offset_mut() = offset;
+                self.results_height.set(height);
+
+                let header_cells = shown.clone().map(|i| {
+                    let mut spans = vec![Span::raw(table.columns[i].to_uppercase())];
+                    if selected_tab.show_types {
+                        spans.push(Span::styled(
+                            format!(" {}", decl_type(table, i)),
+                            Style::default().fg(Color::Indexed(242)).not_bold(),
+                        ));
+                    }
+                    Cell::from(Line::from(spans))
+                });
                 let header = Row::new(header_cells).style(
                     ratatui::style::Style::default()
                         .bold()
@@ -432,28 +1545,53 @@ impl App {
                         .bg(ratatui::style::Color::White),
                 );
 
-                let rows = rows.iter().map(|item| {
-                    let cells = item.iter().map(|c| Cell::from(Text::from(c.to_string())));
+                let rows = table.rows.iter().skip(offset).take(height).map(|item| {
+                    let cells = item[shown.clone()].iter().map(value_cell);
                     Row::new(cells)
                 });
 
-                let widths = [Constraint::Length(5), Constraint::Length(5)];
-                let table = Table::new(rows, widths)
+                let widths = widths[shown.clone()].iter().map(|&w| Constraint::Length(w));
+                let mut table = Table::new(rows, widths)
                     .header(header)
-                    .block(Block::default().borders(Borders::ALL).title("Results"))
-                    .widths(
-                        columns
-                            .iter()
-                            .map(|_| Constraint::Min(10))
-                            .collect::<Vec<_>>(),
-                    );
-                f.render_widget(table, chunks);
+                    .block(Block::default().borders(Borders::ALL).title(title));
+                if self.input_mode == InputMode::Results {
+                    table = table
+                        .row_highlight_style(Style::default().bg(Color::Indexed(236)))
+                        .cell_highlight_style(Style::default().reversed());
+                }
+                let mut visible_state =
+                    TableState::default().with_selected_cell((row - offset, column - first));
+                f.render_stateful_widget(table, chunks, &mut visible_state);
                 return;
             }
+            QueryResult::Execute(info) => {
+                let mut lines = vec![
+                    Line::from(format!(" Rows affected: {}", info.rows_affected))
+                        .style(Style::default().fg(Color::Green)),
+                ];
+                if let Some(rowid) = info.last_insert_rowid {
+                    lines.push(Line::from(format!(" Last insert rowid: {rowid}")));
+                }
+                Paragraph::new(Text::from(lines))
+                    .block(Block::default().borders(Borders::ALL).title(title))
+            }
             QueryResult::Error(err) => {
                 Paragraph::new(Text::from(err.to_string()).style(Style::default().fg(Color::Red)))
-                    .block(Block::default().borders(Borders::ALL).title("Error"))
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title(format!(" Error{counter} ")),
+                    )
             }
+            QueryResult::Cancelled => Paragraph::new(
+                Text::from(" Query cancelled").style(Style::default().fg(Color::Yellow)),
+            )
+            .block(Block::default().borders(Borders::ALL).title(title)),
+            QueryResult::Timeout(timeout) => Paragraph::new(
+                Text::from(format!(" Query timed out after {}s", timeout.as_secs()))
+                    .style(Style::default().fg(Color::Yellow)),
+            )
+            .block(Block::default().borders(Borders::ALL).title(title)),
         };
         f.render_widget(results_block, chunks);
     }
@@ -468,10 +1606,277 @@ impl App {
         f.render_widget(para, area);
     }
 
+    fn render_confirm_quit(&self, f: &mut Frame) {
+        let area = App::popup_area(f.area(), 50, 20);
+        f.render_widget(Clear, area);
+
+        let names = self
+            .connections
+            .iter()
+            .filter(|c| c.in_transaction)
+            .map(|c| c.profile.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let text = Text::from(vec![
+            Line::from(format!(" A transaction is still open on {names}.")),
+            Line::from(" Quit and roll it back?"),
+            Line::from(""),
+            Line::from(" y to quit | any other key to go back")
+                .style(Style::default().fg(Color::Indexed(246))),
+        ]);
+        let block = Block::bordered()
+            .title(" Open transaction ")
+            .border_style(Style::default().fg(Color::Yellow));
+        f.render_widget(
+            Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+            area,
+        );
+    }
+
+    /// Bytes of the selected cell when the inspector shows their hex dump,
+    /// which blobs that can't be decoded always get.
+    fn dumped_blob(&self, inspector: &Inspector) -> Option<&[u8]> {
+        let (table, row, column) = self.tabs[self.selected_tab].selected_cell()?;
+        let content = inspector.content.as_ref()?;
+        match table.rows[row][column].value() {
+            libsql::Value::Blob(bytes) if inspector.hex || !content.is_decodable() => {
+                Some(bytes.as_slice())
+            }
+            _ => None,
+        }
+    }
+
+    /// Title and content of the inspector popup, only the visible lines of a
+    /// hex dump.
+    fn inspector_text(&self, inspector: &Inspector) -> Option<(String, Text<'static>)> {
+        let (table, row, column) = self.tabs[self.selected_tab].selected_cell()?;
+        let values = &table.rows[row];
+
+        if !inspector.row {
+            let mut title = format!(" {} · row {} ", table.columns[column], row + 1);
+            let (libsql::Value::Blob(bytes), Some(content)) =
+                (values[column].value(), &inspector.content)
+            else {
+                return Some((title, Text::from(values[column].full())));
+            };
+
+            title.push_str(&format!("· {content} · {} bytes ", bytes.len()));
+            let text = match self.dumped_blob(inspector) {
+                Some(bytes) => {
+                    let first = inspector.scroll;
+                    let lines = first..first + inspector.height.get() as usize;
+                    Text::from_iter(blob::hex_dump(bytes, lines))
+                }
+                None => Text::from(content.decoded().unwrap_or_default()),
+            };
+            return Some((title, text));
+        }
+
+        let name_width = table.columns.iter().map(|c| c.width()).max().unwrap_or(0);
+        let mut lines = vec![];
+        for (i, (name, value)) in table.columns.iter().zip(values).enumerate() {
+            let style = if i == column {
+                Style::default().fg(Color::Yellow).bold()
+            } else {
+                Style::default().fg(Color::Indexed(246))
+            };
+            let full = value.full();
+            for (j, part) in full.split('\n').enumerate() {
+                let label = if j == 0 { name.as_str() } else { "" };
+                let pad = name_width - label.width();
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{}{label} │ ", " ".repeat(pad)), style),
+                    Span::raw(part.to_string()),
+                ]));
+            }
+        }
+        Some((format!(" Row {} ", row + 1), Text::from(lines)))
+    }
+
+    fn render_inspector(&self, f: &mut Frame, inspector: &Inspector) {
+        let area = App::popup_area(f.area(), 80, 70);
+        inspector.height.set(area.height.saturating_sub(2));
+        inspector.width.set(area.width.saturating_sub(2));
+        let Some((title, text)) = self.inspector_text(inspector) else {
+            return;
+        };
+        f.render_widget(Clear, area);
+
+        if let Some(viewer) = &inspector.json {
+            self.render_json(f, area, &title, viewer, inspector);
+            return;
+        }
+
+        let view = if inspector.row { "cell" } else { "row" };
+        let decodable_blob = inspector
+            .content
+            .as_ref()
+            .is_some_and(blob::Content::is_decodable);
+        let hex = if decodable_blob { " x hex |" } else { "" };
+        let block = Block::bordered()
+            .title(title)
+            .title_bottom(format!(" j/k scroll |{hex} r {view} view | Esc close "));
+
+        // Hex dumps only hold their visible lines, already scrolled.
+        let para = match self.dumped_blob(inspector) {
+            Some(_) => Paragraph::new(text).block(block),
+            None => Paragraph::new(text)
+                .block(block)
+                .wrap(Wrap { trim: false })
+                .scroll((inspector.scroll.min(u16::MAX as usize) as u16, 0)),
+        };
+        f.render_widget(para, area);
+    }
+
+    fn render_json(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        title: &str,
+        viewer: &json::Viewer,
+        inspector: &Inspector,
+    ) {
+        let block = Block::bordered()
+            .title(format!("{title}· JSON "))
+            .title_bottom(" j/k move | Enter fold | y copy path | r row view | Esc close ");
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let [tree_area, path_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(inner);
+
+        let items = viewer.lines().iter().map(|l| ListItem::new(l.line.clone()));
+        let list = List::new(items).highlight_style(Style::default().bg(Color::Indexed(236)));
+        let mut state = ListState::default()
+            .with_offset(inspector.json_offset.get())
+            .with_selected(Some(viewer.cursor));
+        f.render_stateful_widget(list, tree_area, &mut state);
+        inspector.json_offset.set(state.offset());
+
+        let path = match &inspector.status {
+            Some(status) => status.clone(),
+            None => json::json_path(viewer.path()),
+        };
+        f.render_widget(
+            Paragraph::new(format!(" {path}")).style(Style::default().fg(Color::Indexed(246))),
+            path_area,
+        );
+    }
+
+    fn render_param_form(&self, f: &mut Frame, form: &ParamForm) {
+        let area = App::popup_area(f.area(), 60, 50);
+        f.render_widget(Clear, area);
+
+        let block = Block::bordered().title(" Parameters ");
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let [list_area, status_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(2)]).areas(inner);
+
+        let name_width = form.fields.iter().map(|(n, _)| n.len()).max().unwrap_or(0);
+        let items = form.fields.iter().map(|(name, param)| {
+            ListItem::new(Line::from(vec![
+                Span::raw(format!(" {name:name_width$} ")),
+                Span::styled(
+                    format!("{:<8}", param.kind.to_string()),
+                    Style::default().fg(Color::Indexed(246)),
+                ),
+                Span::raw(param.value.clone()),
+            ]))
+        });
+        let list =
+            List::new(items).highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+        let mut state = ListState::default().with_selected(Some(form.selected));
+        f.render_stateful_widget(list, list_area, &mut state);
+
+        let status = match &form.error {
+            Some(err) => Text::from(format!(" {err}")).style(Style::default().fg(Color::Red)),
+            None => Text::from(" Enter to run | Tab next | ←/→ type | Esc to cancel")
+                .style(Style::default().fg(Color::Indexed(246))),
+        };
+        f.render_widget(
+            Paragraph::new(status).wrap(Wrap { trim: false }),
+            status_area,
+        );
+    }
+
+    fn render_switcher(&self, f: &mut Frame, switcher: &Switcher) {
+        let area = App::popup_area(f.area(), 60, 50);
+        f.render_widget(Clear, area);
+
+        let block = Block::bordered().title(" Switch database ");
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let [list_area, status_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(2)]).areas(inner);
+
+        let active = self.active_connection().map(|c| c.profile.location());
+        let items = switcher.entries.iter().map(|entry| {
+            let location = entry.location();
+            let marker = if location.is_some() && location == active {
+                "● "
+            } else if self
+                .connections
+                .iter()
+                .any(|c| Some(c.profile.location()) == location)
+            {
+                "○ "
+            } else {
+                "  "
+            };
+            let detail = match &entry.profile {
+                Ok(profile) if profile.location() == entry.name => "",
+                Ok(profile) => profile.location(),
+                Err(_) => "token unavailable",
+            };
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{marker}{} ", entry.name)),
+                Span::styled(detail, Style::default().fg(Color::Indexed(246))),
+            ]))
+        });
+        let list =
+            List::new(items).highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+        let mut state = ListState::default().with_selected(Some(switcher.selected));
+        f.render_stateful_widget(list, list_area, &mut state);
+
+        let status = match (&switcher.error, &switcher.connecting) {
+            (Some(err), _) => Text::from(format!(" {err}")).style(Style::default().fg(Color::Red)),
+            (None, Some(_)) if switcher.resolving => {
+                Text::from(" Resolving token… | Esc to abandon")
+            }
+            (None, Some(_)) => Text::from(" Connecting… | Esc to abandon"),
+            (None, None) => Text::from(" Enter to connect | Esc to close")
+                .style(Style::default().fg(Color::Indexed(246))),
+        };
+        f.render_widget(
+            Paragraph::new(status).wrap(Wrap { trim: false }),
+            status_area,
+        );
+    }
+
     fn render_footer(&self, f: &mut Frame, area: Rect) {
         let style = Style::default().fg(Color::Indexed(246));
         let block = Block::default().border_style(style).borders(Borders::ALL);
-        let para = Paragraph::new(Text::from("? for help | q to quit".to_string()))
+
+        let mut spans = vec![Span::raw("? for help | q to quit")];
+        let replica = self.active_connection().and_then(|c| c.replica.as_ref());
+        if let Some((replica, err)) = replica.and_then(|r| Some((r, r.error.as_ref()?))) {
+            let retry = match (replica.interval, replica.last_attempt) {
+                (Some(interval), Some(at)) => {
+                    let wait = replica.backoff(interval).saturating_sub(at.elapsed());
+                    format!(", retrying in {}s", wait.as_secs())
+                }
+                _ => String::new(),
+            };
+            spans.push(Span::styled(
+                format!(" | Sync failed{retry}: {err}"),
+                Style::default().fg(Color::Red),
+            ));
+        }
+
+        let para = Paragraph::new(Line::from(spans))
             .block(block)
             .style(style)
             .wrap(Wrap { trim: false });
@@ -503,6 +1908,22 @@ impl App {
         if self.show_help {
             self.render_help(f);
         }
+
+        if self.confirm_quit {
+            self.render_confirm_quit(f);
+        }
+
+        if let Some(switcher) = &self.switcher {
+            self.render_switcher(f, switcher);
+        }
+
+        if let Some(inspector) = &self.inspector {
+            self.render_inspector(f, inspector);
+        }
+
+        if let Some(form) = &self.param_form {
+            self.render_param_form(f, form);
+        }
     }
 }
 
@@ -519,10 +1940,26 @@ impl App {
             Line::from(" D      → clear query"),
             Line::from(" c      → clear results"),
             Line::from(" Ctrl-r → run query"),
+            Line::from(" Ctrl-e → run statement under cursor"),
+            Line::from(" v      → visual mode, Ctrl-r runs selection"),
+            Line::from(" Ctrl-c → cancel query"),
             Line::from(" Ctrl-n → new tab"),
             Line::from(" Ctrl-w → close tab"),
             Line::from(" Ctrl-t → list tables"),
+            Line::from(" Ctrl-s → sync replica"),
+            Line::from(" Ctrl-o → switch tab database"),
+            Line::from(" B / C / R → begin / commit / rollback"),
+            Line::from(" [ / ]  → prev / next statement result"),
             Line::from(" H / L  → prev / next tab"),
+            Line::from(" T      → cycle tab timeout"),
+            Line::from(" M      → load more rows"),
+            Line::from(" Tab    → focus results (hjkl, gg/G, Ctrl-d/u)"),
+            Line::from(" Enter  → show the selected cell in full, JSON as a tree"),
+            Line::from(" r      → show the selected row as a record"),
+            Line::from(" x      → in a blob cell, toggle its hex dump"),
+            Line::from(" < / >  → narrow / widen result column"),
+            Line::from(" =      → fit result columns to content"),
+            Line::from(" t      → show declared column types"),
             Line::from(" q      → quit"),
             Line::from(" ?      → toggle this help"),
             Line::from(""),
@@ -546,6 +1983,8 @@ enum InputMode {
     #[default]
     Normal,
     Insert,
+    Visual,
+    Results,
 }
 
 impl Display for InputMode {
@@ -553,6 +1992,8 @@ impl Display for InputMode {
         match self {
             InputMode::Normal => write!(f, " NORMAL "),
             InputMode::Insert => write!(f, " INSERT "),
+            InputMode::Visual => write!(f, " VISUAL "),
+            InputMode::Results => write!(f, " RESULTS "),
         }
     }
 }
@@ -561,12 +2002,107 @@ enum QueryResult {
     #[default]
     None,
     Table(db::Table),
+    Execute(db::ExecuteInfo),
     Error(String),
+    Cancelled,
+    Timeout(Duration),
+    /// Results of a multi-statement script, up to the first error.
+    Script(Vec<StatementResult>),
+}
+
+impl From<anyhow::Result<db::Output>> for QueryResult {
+    fn from(res: anyhow::Result<db::Output>) -> Self {
+        match res {
+            Ok(db::Output::Rows(table, _)) => QueryResult::Table(table),
+            Ok(db::Output::Execute(info)) => QueryResult::Execute(info),
+            Err(err) => QueryResult::Error(err.to_string()),
+        }
+    }
+}
+
+struct StatementResult {
+    /// Byte range of the statement in the submitted SQL.
+    range: Range<usize>,
+    result: QueryResult,
+    elapsed: Duration,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq)]
 enum Action {
-    Query(String),
+    Query {
+        tab: usize,
+        request: u64,
+        connection: usize,
+        sql: String,
+        params: HashMap<String, libsql::Value>,
+        timeout: Option<Duration>,
+    },
+    Cancel {
+        tab: usize,
+    },
+    /// Closes the cursor of the tab's finished query, whose open statement
+    /// keeps tables of a local database locked.
+    Release {
+        tab: usize,
+    },
+    FetchMore {
+        tab: usize,
+    },
+    Sync(usize),
+    Transaction {
+        id: usize,
+        tab: usize,
+        op: TransactionOp,
+    },
+    /// Resolves the token of the profile, then connects to it.
+    Connect {
+        id: usize,
+        profile: config::Profile,
+    },
+    /// Gives up on a connection still being opened.
+    Abandon {
+        id: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TransactionOp {
+    Begin,
+    Commit,
+    Rollback,
+}
+
+enum Response {
+    Query {
+        tab: usize,
+        request: u64,
+        result: QueryResult,
+        elapsed: Duration,
+    },
+    /// Next page of rows of a query, and whether more are left.
+    Rows {
+        tab: usize,
+        request: u64,
+        rows: Result<(Vec<Vec<db::ValueWrapper>>, bool), String>,
+    },
+    Synced {
+        id: usize,
+        res: Result<db::SyncInfo, String>,
+    },
+    /// Whether a transaction is open on the connection afterwards, failed
+    /// operations included, and why the operation of the tab failed.
+    Transaction {
+        id: usize,
+        tab: usize,
+        open: bool,
+        error: Option<String>,
+    },
+    /// The token of the connecting profile is resolved, connecting is left.
+    TokenResolved { id: usize },
+    Connected {
+        id: usize,
+        res: Result<db::Target, String>,
+    },
 }
 
 #[tokio::main]
@@ -579,65 +2115,585 @@ async fn main() {
 async fn run() -> anyhow::Result<()> {
     dotenv::dotenv().ok();
 
-    let config = config::load_config()?;
-
-    let db = select_database(&config)?;
-
-    let db_tokens = config.cache.database_token.as_ref().ok_or(anyhow::anyhow!(
-        "No database tokens found in config, use `turso db shell DB_NAME` to populate the config",
-    ))?;
-
-    let db_token = db_tokens.get(db.db_id.as_str()).ok_or(anyhow::anyhow!(
-        "No database token found for {}, use `turso db shell {}` to populate the config",
-        db.name,
-        db.name
-    ))?;
-    let url = format!("libsql://{}", db.hostname);
+    let args = cli::Args::parse();
+    let mut profile = args.profile()?;
+    profile.timeout_secs = args.timeout.or(profile.timeout_secs);
+    let target = args.target(&profile)?;
 
-    let db = libsql::Builder::new_remote(url.clone(), db_token.data.clone())
-        .build()
+    let client = target
+        .connect()
+        .await?
+        .with_read_only(profile.read_only)
         .await?;
-    let conn = db.connect()?;
 
-    let client = db::LibSqlClient(conn);
-
-    let (action_tx, mut action_rx) = mpsc::unbounded_channel::<Action>();
-    let (result_tx, result_rx) = mpsc::unbounded_channel::<QueryResult>();
+    let (action_tx, action_rx) = mpsc::unbounded_channel::<Action>();
+    let (result_tx, result_rx) = mpsc::unbounded_channel::<Response>();
 
     let mut app = App {
-        url: url.to_string(),
         input_mode: InputMode::default(),
         action_sender: action_tx,
         res_recv: result_rx,
         tabs: vec![],
         selected_tab: 0,
+        next_tab_id: 0,
+        next_request_id: 0,
         show_help: false,
+        results_height: StdCell::default(),
+        pending_g: false,
+        connections: vec![Connection::new(0, profile, target, args.sync_interval())],
+        next_connection_id: 1,
+        switcher: None,
+        param_form: None,
+        inspector: None,
+        confirm_quit: false,
+        timeout_secs: args.timeout,
     };
     app.new_tab();
+    app.sync();
 
     let terminal = ratatui::init();
 
-    let client = client.clone();
-    tokio::spawn(async move {
-        while let Some(action) = action_rx.recv().await {
-            match action {
-                Action::Query(query) => {
-                    let res = client.query_owned(&query).await;
-                    let res = match res {
-                        Ok(table) => QueryResult::Table(table),
-                        Err(err) => QueryResult::Error(err.to_string()),
+    let clients = HashMap::from([(0, client)]);
+    let worker = tokio::spawn(worker(clients, args.page_size.max(1), action_rx, result_tx));
+
+    let app_result = app.run(terminal);
+
+    ratatui::restore();
+
+    // The app dropped its sender, wait for the worker to cancel what's still
+    // running so a busy query doesn't keep the process alive.
+    let _ = worker.await;
+
+    app_result
+}
+
+/// A running query, cancelled when dropped.
+struct RunningQuery {
+    cancel: oneshot::Sender<()>,
+    /// Stops a script at its next statement.
+    cancelled: Arc<AtomicBool>,
+    /// Asks for the next page of rows.
+    more: mpsc::UnboundedSender<()>,
+    client: db::LibSqlClient,
+}
+
+impl Drop for RunningQuery {
+    fn drop(&mut self) {
+        // Local queries block their thread while a statement runs, interrupting
+        // makes it return early, and the flag keeps the script from running
+        // its next statement. Dropping `cancel` takes care of the rest.
+        // The connection is shared with other tabs, whose statements are left
+        // running: this one then runs to its end, its result dropped.
+        if !self.cancel.is_closed() {
+            self.cancelled.store(true, Ordering::SeqCst);
+            let _ = self.client.interrupt_alone();
+        }
+    }
+}
+
+/// Reports whether a transaction is left open on the connection once the
+/// statements of a query stop, however they stop: typed `BEGIN`, `COMMIT` or
+/// `ROLLBACK`, and interrupted statements rolling back, all change it.
+struct TransactionReport {
+    id: usize,
+    tab: usize,
+    client: db::LibSqlClient,
+    result_tx: mpsc::UnboundedSender<Response>,
+}
+
+impl Drop for TransactionReport {
+    fn drop(&mut self) {
+        let _ = self.result_tx.send(Response::Transaction {
+            id: self.id,
+            tab: self.tab,
+            open: !self.client.is_autocommit(),
+            error: None,
+        });
+    }
+}
+
+/// Runs the query, giving up once the timeout has passed.
+async fn run_query(
+    client: db::LibSqlClient,
+    sql: String,
+    params: HashMap<String, libsql::Value>,
+    timeout: Option<Duration>,
+    page_size: usize,
+    cancelled: Arc<AtomicBool>,
+    report: TransactionReport,
+) -> (QueryResult, Option<db::Cursor>) {
+    let task_client = client.clone();
+    let task_cancelled = cancelled.clone();
+    // The script outlives the query when a local one times out, it reports
+    // once its statement is done.
+    let script = async move {
+        let _report = report;
+        run_script(&task_client, &sql, &params, page_size, &task_cancelled).await
+    };
+    let query = async {
+        if !client.is_local() {
+            // Remote statements are requests, dropping the future stops them.
+            return script.await;
+        }
+        // Local queries don't yield while SQLite works, so each query gets a
+        // blocking thread instead of starving the runtime.
+        let runtime = tokio::runtime::Handle::current();
+        tokio::task::spawn_blocking(move || runtime.block_on(script))
+            .await
+            .unwrap_or_else(|err| (QueryResult::Error(err.to_string()), None))
+    };
+    let expired = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+
+    tokio::select! {
+        res = query => res,
+        _ = expired => {
+            // Statements of other tabs sharing the connection are left
+            // running, this one then finishes in the background but the
+            // script stops there.
+            cancelled.store(true, Ordering::SeqCst);
+            let _ = client.interrupt_alone();
+            (QueryResult::Timeout(timeout.unwrap_or_default()), None)
+        }
+    }
+}
+
+/// Runs the statements of the script one by one, stopping at the first error
+/// or once cancelled.
+///
+/// Only a single statement gets a cursor to fetch more rows with.
+async fn run_script(
+    client: &db::LibSqlClient,
+    sql: &str,
+    params: &HashMap<String, libsql::Value>,
+    page_size: usize,
+    cancelled: &AtomicBool,
+) -> (QueryResult, Option<db::Cursor>) {
+    let statements = sql::statements(sql);
+    let mut bound = params::bind(sql, params).into_iter();
+    if statements.len() <= 1 {
+        let params = bound.next().unwrap_or_default();
+        return match client.run(sql, params, page_size).await {
+            Ok(db::Output::Rows(table, cursor)) => (QueryResult::Table(table), cursor),
+            res => (res.into(), None),
+        };
+    }
+
+    let mut results = Vec::with_capacity(statements.len());
+    for range in statements {
+        if cancelled.load(Ordering::SeqCst) {
+            return (QueryResult::Cancelled, None);
+        }
+        let started = Instant::now();
+        let statement = &sql[range.clone()];
+        let result = QueryResult::from(
+            client
+                .run(statement, bound.next().unwrap_or_default(), page_size)
+                .await,
+        );
+        let failed = matches!(result, QueryResult::Error(_));
+        results.push(StatementResult {
+            range,
+            result,
+            elapsed: started.elapsed(),
+        });
+        if failed {
+            break;
+        }
+    }
+    (QueryResult::Script(results), None)
+}
+
+/// Fetches a page of rows each time more are asked for, until the cursor is
+/// exhausted or the query is dropped.
+async fn fetch_pages(
+    mut cursor: db::Cursor,
+    page_size: usize,
+    mut more_rx: mpsc::UnboundedReceiver<()>,
+    tab: usize,
+    request: u64,
+    result_tx: mpsc::UnboundedSender<Response>,
+) {
+    while more_rx.recv().await.is_some() {
+        let runtime = tokio::runtime::Handle::current();
+        let fetched = tokio::task::spawn_blocking(move || {
+            let res = runtime.block_on(cursor.fetch(page_size));
+            (cursor, res)
+        })
+        .await;
+        let Ok((returned, res)) = fetched else {
+            return;
+        };
+        cursor = returned;
+
+        let done = !matches!(res, Ok((_, true)));
+        let _ = result_tx.send(Response::Rows {
+            tab,
+            request,
+            rows: res.map_err(|err| err.to_string()),
+        });
+        if done {
+            return;
+        }
+    }
+}
+
+/// Forgets the transaction of the connection once a typed `COMMIT` or
+/// `ROLLBACK` ended it, dropping it then does nothing.
+fn end_typed_transaction(transactions: &mut HashMap<usize, libsql::Transaction>, id: usize) {
+    if transactions.get(&id).is_some_and(|tx| tx.is_autocommit()) {
+        transactions.remove(&id);
+    }
+}
+
+async fn worker(
+    mut clients: HashMap<usize, db::LibSqlClient>,
+    page_size: usize,
+    mut action_rx: mpsc::UnboundedReceiver<Action>,
+    result_tx: mpsc::UnboundedSender<Response>,
+) {
+    let mut running: HashMap<usize, RunningQuery> = HashMap::new();
+    // Open transactions by connection, rolled back when dropped.
+    let mut transactions: HashMap<usize, libsql::Transaction> = HashMap::new();
+    // Connections being opened, each in its own task so that a slow token
+    // command or server doesn't hold up the queries of the other tabs.
+    let mut connecting: HashMap<usize, tokio::task::JoinHandle<()>> = HashMap::new();
+    let (connected_tx, mut connected_rx) =
+        mpsc::unbounded_channel::<(usize, Result<(db::Target, db::LibSqlClient), String>)>();
+
+    loop {
+        let action = tokio::select! {
+            action = action_rx.recv() => match action {
+                Some(action) => action,
+                None => break,
+            },
+            Some((id, res)) = connected_rx.recv() => {
+                // Abandoned connections are dropped.
+                if connecting.remove(&id).is_some() {
+                    let res = res.map(|(target, client)| {
+                        clients.insert(id, client);
+                        target
+                    });
+                    let _ = result_tx.send(Response::Connected { id, res });
+                }
+                continue;
+            }
+        };
+
+        match action {
+            Action::Query {
+                tab,
+                request,
+                connection,
+                sql,
+                params,
+                timeout,
+            } => {
+                // A newer query from the same tab supersedes the running one.
+                running.remove(&tab);
+                end_typed_transaction(&mut transactions, connection);
+
+                // Statements of a connection with an open transaction run in it.
+                let client =
+                    clients
+                        .get(&connection)
+                        .map(|client| match transactions.get(&connection) {
+                            Some(tx) => client.in_transaction(tx),
+                            None => client.clone(),
+                        });
+                let Some(client) = client else {
+                    let _ = result_tx.send(Response::Query {
+                        tab,
+                        request,
+                        result: QueryResult::Error("Connection is not open".to_string()),
+                        elapsed: Duration::ZERO,
+                    });
+                    continue;
+                };
+
+                let (cancel_tx, mut cancel_rx) = oneshot::channel();
+                let (more_tx, more_rx) = mpsc::unbounded_channel();
+                let cancelled = Arc::new(AtomicBool::new(false));
+                let result_tx = result_tx.clone();
+                let report = TransactionReport {
+                    id: connection,
+                    tab,
+                    client: client.clone(),
+                    result_tx: result_tx.clone(),
+                };
+                let query = run_query(
+                    client.clone(),
+                    sql,
+                    params,
+                    timeout,
+                    page_size,
+                    cancelled.clone(),
+                    report,
+                );
+                tokio::spawn(async move {
+                    let started = Instant::now();
+                    let (result, cursor) = tokio::select! {
+                        result = query => result,
+                        _ = &mut cancel_rx => return,
+                    };
+                    let _ = result_tx.send(Response::Query {
+                        tab,
+                        request,
+                        result,
+                        elapsed: started.elapsed(),
+                    });
+
+                    // Pages are quick to fetch, dropping the query is enough
+                    // to stop them without interrupting the connection.
+                    drop(cancel_rx);
+                    if let Some(cursor) = cursor {
+                        fetch_pages(cursor, page_size, more_rx, tab, request, result_tx).await;
+                    }
+                });
+                running.insert(
+                    tab,
+                    RunningQuery {
+                        cancel: cancel_tx,
+                        cancelled,
+                        more: more_tx,
+                        client,
+                    },
+                );
+            }
+            Action::Cancel { tab } => {
+                running.remove(&tab);
+            }
+            Action::Release { tab } => {
+                if running.get(&tab).is_some_and(|q| q.cancel.is_closed()) {
+                    running.remove(&tab);
+                }
+            }
+            Action::FetchMore { tab } => {
+                if let Some(query) = running.get(&tab) {
+                    let _ = query.more.send(());
+                }
+            }
+            Action::Connect { id, profile } => {
+                let result_tx = result_tx.clone();
+                let connected_tx = connected_tx.clone();
+                let task = tokio::spawn(async move {
+                    let read_only = profile.read_only;
+                    // Token commands block, keep them off the runtime.
+                    let resolved = match tokio::task::spawn_blocking(move || profile.target()).await
+                    {
+                        Ok(res) => res,
+                        Err(err) => Err(err.into()),
+                    };
+                    let res = match resolved {
+                        Ok(target) => {
+                            let _ = result_tx.send(Response::TokenResolved { id });
+                            match target.connect().await {
+                                Ok(client) => match client.with_read_only(read_only).await {
+                                    Ok(client) => Ok((target, client)),
+                                    Err(err) => Err(err.to_string()),
+                                },
+                                Err(err) => Err(err.to_string()),
+                            }
+                        }
+                        Err(err) => Err(err.to_string()),
                     };
-                    let _ = result_tx.send(res);
+                    let _ = connected_tx.send((id, res));
+                });
+                connecting.insert(id, task);
+            }
+            Action::Abandon { id } => {
+                if let Some(task) = connecting.remove(&id) {
+                    task.abort();
                 }
             }
+            Action::Transaction { id, tab, op } => {
+                end_typed_transaction(&mut transactions, id);
+                let error = match (op, clients.get(&id)) {
+                    (_, None) => Some("Connection is not open".to_string()),
+                    (TransactionOp::Begin, Some(client)) => match client.transaction().await {
+                        Ok(tx) => {
+                            transactions.insert(id, tx);
+                            None
+                        }
+                        Err(err) => Some(err.to_string()),
+                    },
+                    // Both consume the transaction, a failed commit rolling it
+                    // back when dropped.
+                    (op, Some(client)) => match transactions.remove(&id) {
+                        Some(tx) if op == TransactionOp::Commit => {
+                            tx.commit().await.err().map(|err| {
+                                format!("Commit failed, the transaction was rolled back: {err}")
+                            })
+                        }
+                        Some(tx) => tx.rollback().await.err().map(|err| err.to_string()),
+                        // Begun by a typed `BEGIN`, it's ended the same way.
+                        None => {
+                            let sql = match op {
+                                TransactionOp::Commit => "COMMIT",
+                                _ => "ROLLBACK",
+                            };
+                            client
+                                .execute(sql, ())
+                                .await
+                                .err()
+                                .map(|err| err.to_string())
+                        }
+                    },
+                };
+                let open = match (transactions.get(&id), clients.get(&id)) {
+                    (Some(tx), _) => !tx.is_autocommit(),
+                    (None, Some(client)) => !client.is_autocommit(),
+                    (None, None) => false,
+                };
+                let _ = result_tx.send(Response::Transaction {
+                    id,
+                    tab,
+                    open,
+                    error,
+                });
+            }
+            Action::Sync(id) => {
+                // Syncing can take a while, queries keep running meanwhile.
+                let client = clients.get(&id).cloned();
+                let result_tx = result_tx.clone();
+                tokio::spawn(async move {
+                    let res = match client {
+                        Some(client) => client.sync().await.map_err(|err| err.to_string()),
+                        None => Err("Connection is not open".to_string()),
+                    };
+                    let _ = result_tx.send(Response::Synced { id, res });
+                });
+            }
         }
-    });
+    }
+}
 
-    let app_result = app.run(terminal);
+fn replica_span(replica: &ReplicaState) -> Span<'static> {
+    if replica.syncing {
+        return Span::styled("syncing…", Style::default().fg(Color::Yellow));
+    }
+    if replica.error.is_some() {
+        return Span::styled("sync failed", Style::default().fg(Color::Red));
+    }
+    match &replica.last_sync {
+        Some((at, info)) => {
+            let frame = info
+                .frame_no
+                .map_or("-".to_string(), |frame| frame.to_string());
+            Span::styled(
+                format!(
+                    "synced {}s ago #{frame} (+{})",
+                    at.elapsed().as_secs(),
+                    info.frames_synced
+                ),
+                Style::default().fg(Color::Green),
+            )
+        }
+        None => Span::styled("not synced", Style::default().fg(Color::Indexed(246))),
+    }
+}
 
-    ratatui::restore();
+/// Byte offset of the `char_index`th character, or the end of the text.
+fn byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(i, _)| i)
+}
 
-    app_result
+/// Splits the text into lines, styling the bytes in `range`.
+fn highlight(text: &str, range: Range<usize>, style: Style) -> Text<'_> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in text.split('\n') {
+        let end = offset + line.len();
+        let start_hl = range.start.clamp(offset, end) - offset;
+        let end_hl = range.end.clamp(offset, end) - offset;
+        lines.push(Line::from(vec![
+            Span::raw(&line[..start_hl]),
+            Span::styled(&line[start_hl..end_hl], style),
+            Span::raw(&line[end_hl..]),
+        ]));
+        offset = end + 1;
+    }
+    Text::from(lines)
+}
+
+/// Widest a result column gets unless fitted to its content.
+const MAX_COLUMN_WIDTH: u16 = 40;
+const MIN_COLUMN_WIDTH: u16 = 3;
+
+/// Copies text to the system clipboard through the terminal, with an OSC 52
+/// escape sequence.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
+
+/// Declared type of a result column, `?` when unknown.
+fn decl_type(table: &db::Table, column: usize) -> &str {
+    table
+        .decl_types
+        .get(column)
+        .and_then(Option::as_deref)
+        .unwrap_or("?")
+}
+
+/// Result cell styled by the type of its value: dim NULLs, right-aligned
+/// numbers and colored blobs.
+fn value_cell(value: &db::ValueWrapper) -> Cell<'static> {
+    let text = value.to_string();
+    match value.value() {
+        libsql::Value::Null => {
+            Cell::from(text).style(Style::default().fg(Color::Indexed(242)).italic())
+        }
+        libsql::Value::Integer(_) | libsql::Value::Real(_) => {
+            Cell::from(Line::from(text).right_aligned())
+        }
+        libsql::Value::Blob(_) => Cell::from(text).style(Style::default().fg(Color::Magenta)),
+        libsql::Value::Text(_) => Cell::from(text),
+    }
+}
+
+/// Width of each result column, from its header, with the declared type when
+/// shown, and the cells of the first rows.
+fn column_widths(table: &db::Table, fit: bool, types: bool) -> Vec<u16> {
+    let max = if fit { u16::MAX } else { MAX_COLUMN_WIDTH };
+    table
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let header = if types {
+                c.width() + 1 + decl_type(table, i).width()
+            } else {
+                c.width()
+            };
+            let width = header.max(table.cell_widths.get(i).copied().unwrap_or(0));
+            (width.min(max as usize) as u16).max(MIN_COLUMN_WIDTH)
+        })
+        .collect()
+}
+
+/// Width taken by the columns, including the space between them.
+fn span_width(widths: &[u16]) -> u16 {
+    widths
+        .iter()
+        .fold(0u16, |sum, &w| sum.saturating_add(w))
+        .saturating_add(widths.len().saturating_sub(1).min(u16::MAX as usize) as u16)
+}
+
+/// Formats a duration as milliseconds, or seconds past one second.
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{}ms", duration.as_millis())
+    } else {
+        format!("{:.2}s", duration.as_secs_f64())
+    }
 }
 
 fn wrap_text(text: &str, max_width: u16) -> Vec<String> {
@@ -685,18 +2741,218 @@ mod tests {
 
     fn mock_app() -> App {
         let (action_tx, _) = mpsc::unbounded_channel::<Action>();
-        let (_, result_rx) = mpsc::unbounded_channel::<QueryResult>();
+        let (_, result_rx) = mpsc::unbounded_channel::<Response>();
 
         App {
-            url: "".to_string(),
             input_mode: InputMode::default(),
             action_sender: action_tx,
             res_recv: result_rx,
             tabs: vec![],
             selected_tab: 0,
+            next_tab_id: 0,
+            next_request_id: 0,
             show_help: false,
+            results_height: StdCell::default(),
+            pending_g: false,
+            connections: vec![],
+            next_connection_id: 0,
+            switcher: None,
+            param_form: None,
+            inspector: None,
+            confirm_quit: false,
+            timeout_secs: None,
+        }
+    }
+    #[test]
+    fn test_query_result_routed_to_tab() {
+        let mut app = mock_app();
+        app.new_tab();
+        app.new_tab();
+
+        app.selected_tab = 0;
+        app.send_query("SELECT 1".to_string(), HashMap::new());
+        let first = app.tabs[0].pending.unwrap();
+        app.selected_tab = 1;
+        app.send_query("SELECT 2".to_string(), HashMap::new());
+        let second = app.tabs[1].pending.unwrap();
+        app.delete_tab();
+
+        app.handle_response(Response::Query {
+            tab: 1,
+            request: second,
+            result: QueryResult::Error("closed".to_string()),
+            elapsed: Duration::ZERO,
+        });
+        app.handle_response(Response::Query {
+            tab: 0,
+            request: first,
+            result: QueryResult::Error("first".to_string()),
+            elapsed: Duration::ZERO,
+        });
+
+        assert_eq!(app.tabs.len(), 1);
+        assert!(matches!(&app.tabs[0].query_result, QueryResult::Error(e) if e == "first"));
+        assert_eq!(app.tabs[0].pending, None);
+    }
+
+    #[tokio::test]
+    async fn test_run_script_stops_at_error() {
+        let target = db::Target::Local {
+            path: ":memory:".to_string(),
+        };
+        let client = target.connect().await.unwrap();
+        let sql = "CREATE TABLE t (a);\nINSERT INTO t VALUES (1);\nSELECT nope FROM t;\nSELECT 1";
+
+        let (QueryResult::Script(results), _) =
+            run_script(&client, sql, &HashMap::new(), 100, &AtomicBool::new(false)).await
+        else {
+            panic!("expected a script result");
+        };
+        assert_eq!(results.len(), 3);
+        assert_eq!(&sql[results[2].range.clone()], "SELECT nope FROM t");
+        assert!(matches!(results[1].result, QueryResult::Execute(_)));
+        assert!(matches!(results[2].result, QueryResult::Error(_)));
+
+        // A cancelled script runs none of its remaining statements.
+        let (result, _) = run_script(
+            &client,
+            "INSERT INTO t VALUES (2); INSERT INTO t VALUES (3)",
+            &HashMap::new(),
+            100,
+            &AtomicBool::new(true),
+        )
+        .await;
+        assert!(matches!(result, QueryResult::Cancelled));
+        let Ok(db::Output::Rows(table, _)) = client.run("SELECT * FROM t", vec![], 10).await else {
+            panic!("expected rows");
+        };
+        assert_eq!(table.rows.len(), 1);
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut tab = Tab {
+            input: "SELECT 'é' FROM t".to_string(),
+            ..Tab::new(0, "Query 1".to_string(), 0)
+        };
+        tab.anchor = 9;
+        tab.char_index = 7;
+        assert_eq!(&tab.input[tab.selection()], "'é'");
+
+        tab.anchor = 0;
+        tab.char_index = 0;
+        assert_eq!(&tab.input[tab.selection()], "S");
+    }
+
+    #[test]
+    fn test_failed_statement() {
+        let mut app = mock_app();
+        app.tabs.push(Tab {
+            input: "SELECT 1; SELECT nope".to_string(),
+            char_index: 21,
+            query_result: QueryResult::Script(vec![StatementResult {
+                range: 10..21,
+                result: QueryResult::Error("no such column: nope".to_string()),
+                elapsed: Duration::ZERO,
+            }]),
+            ..Tab::new(0, "Query 1".to_string(), 0)
+        });
+        assert_eq!(app.tabs[0].failed_statement(), Some(10..21));
+
+        app.delete_last_char();
+        assert_eq!(app.tabs[0].failed_statement(), None);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_micros(2500)), "2ms");
+        assert_eq!(format_duration(Duration::from_millis(1234)), "1.23s");
+    }
+
+    #[test]
+    fn test_cycle_timeout() {
+        let mut tab = Tab::new(0, "Query 1".to_string(), 0);
+        let mut seen = vec![];
+        for _ in 0..=TAB_TIMEOUTS.len() {
+            tab.cycle_timeout();
+            seen.push(tab.timeout.map(|t| t.as_secs()));
         }
+        assert_eq!(seen, vec![Some(5), Some(30), Some(60), Some(300), None]);
+    }
+
+    #[test]
+    fn test_sync_backoff() {
+        let interval = Duration::from_secs(10);
+        let mut replica = ReplicaState {
+            interval: Some(interval),
+            ..Default::default()
+        };
+        assert!(replica.sync_due());
+
+        replica.last_attempt = Some(Instant::now());
+        replica.failures = 2;
+        assert_eq!(replica.backoff(interval), Duration::from_secs(40));
+        assert!(!replica.sync_due());
+
+        replica.failures = 10;
+        assert_eq!(replica.backoff(interval), MAX_SYNC_BACKOFF);
+    }
+
+    #[test]
+    fn test_move_cell() {
+        let mut tab = Tab {
+            query_result: QueryResult::Table(db::Table::new(
+                vec!["a".to_string(), "b".to_string()],
+                vec![],
+                (0..3).map(|_| vec![]).collect(),
+                false,
+            )),
+            ..Tab::new(0, "Query 1".to_string(), 0)
+        };
+        tab.reset_selection();
+        let selected = |tab: &Tab| tab.table_state.borrow().selected_cell();
+
+        tab.move_cell(1, 1);
+        assert_eq!(selected(&tab), Some((1, 1)));
+        tab.move_cell(isize::MAX, 5);
+        assert_eq!(selected(&tab), Some((2, 1)));
+        assert!(tab.at_last_row());
+        tab.move_cell(isize::MIN, -1);
+        assert_eq!(selected(&tab), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_column_widths() {
+        let table = db::Table::new(
+            vec!["a".to_string(), "x".repeat(50)],
+            vec![Some("INTEGER".to_string())],
+            vec![],
+            false,
+        );
+        assert_eq!(column_widths(&table, false, false), vec![3, 40]);
+        assert_eq!(column_widths(&table, true, false), vec![3, 50]);
+        assert_eq!(column_widths(&table, true, true), vec![9, 52]);
+        assert_eq!(span_width(&[u16::MAX, 3]), u16::MAX);
+
+        let mut tab = Tab {
+            query_result: QueryResult::Table(table),
+            ..Tab::new(0, "Query 1".to_string(), 0)
+        };
+        tab.reset_selection();
+        tab.resize_column(4);
+        tab.resize_column(-10);
+        tab.move_cell(0, 1);
+        tab.resize_column(2);
+        let widths = |tab: &Tab| match tab.shown_result() {
+            QueryResult::Table(table) => tab.result_widths(table),
+            _ => unreachable!(),
+        };
+        assert_eq!(widths(&tab), vec![3, 42]);
+
+        tab.toggle_fit_columns();
+        assert_eq!(widths(&tab), vec![3, 50]);
     }
+
     #[test]
     fn test_move_next_sql() {
         let mut app = mock_app();
@@ -704,10 +2960,8 @@ mod tests {
         let input = "SELECT * FROM todos";
         let expected = ['S', '*', 'F', 't', 's'];
         let tab = Tab {
-            name: "Query 1".to_string(),
             input: input.to_string(),
-            char_index: 0,
-            query_result: QueryResult::default(),
+            ..Tab::new(0, "Query 1".to_string(), 0)
         };
         app.tabs.push(tab);
         let chars = input.chars().collect::<Vec<char>>();
@@ -730,10 +2984,8 @@ mod tests {
             '.', 'm', '(', 't', '|', 'f', '!', '{', '"', 't', '.', 'n', ')',
         ];
         let tab = Tab {
-            name: "Query 1".to_string(),
             input: input.to_string(),
-            char_index: 0,
-            query_result: QueryResult::default(),
+            ..Tab::new(0, "Query 1".to_string(), 0)
         };
         app.tabs.push(tab);
         let chars = input.chars().collect::<Vec<char>>();
@@ -757,10 +3009,9 @@ mod tests {
         ];
 
         let tab = Tab {
-            name: "Query 1".to_string(),
             input: input.to_string(),
             char_index: input.len(),
-            query_result: QueryResult::default(),
+            ..Tab::new(0, "Query 1".to_string(), 0)
         };
         app.tabs.push(tab);
         let chars = input.chars().collect::<Vec<char>>();
@@ -0,0 +1,196 @@
+use crate::sql::{self, Placeholder};
+use anyhow::Context;
+use libsql::Value;
+use std::{collections::HashMap, fmt::Display};
+
+/// Type a placeholder value is bound as.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    #[default]
+    Text,
+    Integer,
+    Real,
+    Blob,
+    Null,
+}
+
+impl Kind {
+    const ALL: [Kind; 5] = [
+        Kind::Text,
+        Kind::Integer,
+        Kind::Real,
+        Kind::Blob,
+        Kind::Null,
+    ];
+
+    pub fn cycle(self, forward: bool) -> Self {
+        let i = Self::ALL.iter().position(|&k| k == self).unwrap_or(0);
+        let len = Self::ALL.len();
+        let next = if forward { i + 1 } else { i + len - 1 };
+        Self::ALL[next % len]
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kind::Text => write!(f, "text"),
+            Kind::Integer => write!(f, "integer"),
+            Kind::Real => write!(f, "real"),
+            Kind::Blob => write!(f, "blob"),
+            Kind::Null => write!(f, "null"),
+        }
+    }
+}
+
+/// A placeholder value as typed in the prompt.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Param {
+    pub kind: Kind,
+    pub value: String,
+}
+
+impl Param {
+    pub fn to_value(&self) -> anyhow::Result<Value> {
+        let value = self.value.trim();
+        match self.kind {
+            Kind::Text => Ok(Value::Text(self.value.clone())),
+            Kind::Integer => value
+                .parse()
+                .map(Value::Integer)
+                .with_context(|| format!("{value:?} is not an integer")),
+            Kind::Real => value
+                .parse()
+                .map(Value::Real)
+                .with_context(|| format!("{value:?} is not a real")),
+            Kind::Blob => decode_hex(value).map(Value::Blob),
+            Kind::Null => Ok(Value::Null),
+        }
+    }
+}
+
+/// Decodes hex digits, optionally written as a `x'…'` literal.
+fn decode_hex(s: &str) -> anyhow::Result<Vec<u8>> {
+    let digits = s
+        .strip_prefix(['x', 'X'])
+        .and_then(|s| s.strip_prefix('\'')?.strip_suffix('\''))
+        .unwrap_or(s);
+    if !digits.len().is_multiple_of(2) {
+        anyhow::bail!("{s:?} is not a hex blob, expected an even number of digits");
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            digits
+                .get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .with_context(|| format!("{s:?} is not a hex blob"))
+        })
+        .collect()
+}
+
+/// Placeholder names of each statement of a script in parameter index order,
+/// none for the skipped indexes.
+///
+/// Every `?` is a parameter of its own, named after its position in the
+/// script so that it's told apart from the numbered ones.
+fn statement_names(script: &str) -> Vec<Vec<Option<String>>> {
+    let mut anonymous = 0;
+    sql::statements(script)
+        .into_iter()
+        .map(|range| {
+            sql::placeholders(&script[range])
+                .into_iter()
+                .map(|slot| {
+                    slot.map(|placeholder| match placeholder {
+                        Placeholder::Named(name) => name,
+                        Placeholder::Anonymous => {
+                            anonymous += 1;
+                            format!("? ({anonymous})")
+                        }
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Distinct placeholder names of a script, statement by statement.
+pub fn names(script: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for name in statement_names(script).into_iter().flatten().flatten() {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Values for the placeholders of each statement of a script in parameter
+/// index order, NULL for the ones without a value.
+pub fn bind(script: &str, values: &HashMap<String, Value>) -> Vec<Vec<Value>> {
+    statement_names(script)
+        .into_iter()
+        .map(|names| {
+            names
+                .iter()
+                .map(|name| {
+                    name.as_ref()
+                        .and_then(|name| values.get(name))
+                        .cloned()
+                        .unwrap_or(Value::Null)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_value() {
+        let param = |kind, value: &str| {
+            Param {
+                kind,
+                value: value.to_string(),
+            }
+            .to_value()
+        };
+
+        assert_eq!(param(Kind::Integer, " 42 ").unwrap(), Value::Integer(42));
+        assert_eq!(param(Kind::Real, "1.5").unwrap(), Value::Real(1.5));
+        assert_eq!(param(Kind::Text, " a ").unwrap(), Value::Text(" a ".into()));
+        assert_eq!(param(Kind::Null, "x").unwrap(), Value::Null);
+        assert_eq!(
+            param(Kind::Blob, "x'00fF'").unwrap(),
+            Value::Blob(vec![0x00, 0xff])
+        );
+        assert!(param(Kind::Integer, "1.5").is_err());
+        assert!(param(Kind::Blob, "abc").is_err());
+        assert!(param(Kind::Blob, "zz").is_err());
+    }
+
+    #[test]
+    fn test_bind() {
+        let script = "SELECT :id, ?; SELECT ?1, ?; SELECT ?3";
+        assert_eq!(names(script), vec![":id", "? (1)", "?1", "? (2)", "?3"]);
+
+        let values = HashMap::from([
+            ("?1".to_string(), Value::Integer(1)),
+            (":id".to_string(), Value::Integer(7)),
+            ("? (2)".to_string(), Value::Integer(2)),
+            ("?3".to_string(), Value::Integer(3)),
+        ]);
+        assert_eq!(
+            bind(script, &values),
+            vec![
+                vec![Value::Integer(7), Value::Null],
+                vec![Value::Integer(1), Value::Integer(2)],
+                vec![Value::Null, Value::Null, Value::Integer(3)],
+            ]
+        );
+    }
+}
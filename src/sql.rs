@@ -0,0 +1,397 @@
+use std::ops::Range;
+
+/// Splits SQL into its words, skipping string literals, quoted identifiers
+/// and comments.
+pub fn words(sql: &str) -> Vec<&str> {
+    let bytes = sql.as_bytes();
+    let mut words = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if let Some(end) = skip_literal(bytes, i) {
+            i = end;
+        } else if is_word_start(bytes[i]) {
+            let end = word_end(bytes, i);
+            words.push(&sql[i..end]);
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+
+    words
+}
+
+/// Splits a script into its statements, returning their byte ranges without
+/// the `;` separators and surrounding whitespace.
+///
+/// Semicolons inside trigger bodies don't end the statement, and statements
+/// made only of comments are dropped.
+pub fn statements(sql: &str) -> Vec<Range<usize>> {
+    let bytes = sql.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut words = Vec::new();
+    // BEGIN / CASE ... END nesting, only tracked inside CREATE TRIGGER.
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i <= bytes.len() {
+        if i < bytes.len() {
+            if let Some(end) = skip_literal(bytes, i) {
+                i = end;
+                continue;
+            }
+            if is_word_start(bytes[i]) {
+                let end = word_end(bytes, i);
+                let word = &sql[i..end];
+                words.push(word);
+                if is_trigger(&words) {
+                    if word.eq_ignore_ascii_case("BEGIN") || word.eq_ignore_ascii_case("CASE") {
+                        depth += 1;
+                    } else if word.eq_ignore_ascii_case("END") {
+                        depth = depth.saturating_sub(1);
+                    }
+                }
+                i = end;
+                continue;
+            }
+            if bytes[i] != b';' || depth > 0 {
+                i += 1;
+                continue;
+            }
+        }
+
+        if !words.is_empty() {
+            let statement = &sql[start..i];
+            let leading = statement.len() - statement.trim_start().len();
+            statements.push(start + leading..start + statement.trim_end().len());
+        }
+        words.clear();
+        depth = 0;
+        i += 1;
+        start = i;
+    }
+
+    statements
+}
+
+/// Range of the statement at `offset`, or of the one before it when the
+/// offset sits between two statements.
+pub fn statement_at(sql: &str, offset: usize) -> Option<Range<usize>> {
+    let statements = statements(sql);
+    statements
+        .iter()
+        .rev()
+        .find(|r| r.start <= offset)
+        .or(statements.first())
+        .cloned()
+}
+
+/// Placeholder taking a parameter index of a statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Placeholder {
+    /// `?`, taking the index after the largest one so far.
+    Anonymous,
+    /// `?1`, `:id`, `@id` or `$id`.
+    Named(String),
+}
+
+/// Placeholder of each parameter index of a statement, none for the indexes
+/// skipped by numbered placeholders, e.g. the first two of `SELECT ?3`.
+pub fn placeholders(sql: &str) -> Vec<Option<Placeholder>> {
+    let bytes = sql.as_bytes();
+    let mut slots: Vec<Option<Placeholder>> = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if let Some(end) = skip_literal(bytes, i) {
+            i = end;
+            continue;
+        }
+        match bytes[i] {
+            b'?' => {
+                let mut end = i + 1;
+                while end < bytes.len() && bytes[end].is_ascii_digit() {
+                    end += 1;
+                }
+                match sql[i + 1..end].parse::<usize>() {
+                    Ok(index) if index > 0 => {
+                        if slots.len() < index {
+                            slots.resize(index, None);
+                        }
+                        slots[index - 1]
+                            .get_or_insert_with(|| Placeholder::Named(sql[i..end].to_string()));
+                    }
+                    _ => slots.push(Some(Placeholder::Anonymous)),
+                }
+                i = end;
+            }
+            b':' | b'@' | b'$'
+                if bytes
+                    .get(i + 1)
+                    .is_some_and(|&c| c.is_ascii_alphanumeric() || c == b'_') =>
+            {
+                let end = word_end(bytes, i + 1);
+                let name = Placeholder::Named(sql[i..end].to_string());
+                if !slots.iter().flatten().any(|p| *p == name) {
+                    slots.push(Some(name));
+                }
+                i = end;
+            }
+            c if is_word_start(c) => i = word_end(bytes, i),
+            _ => i += 1,
+        }
+    }
+
+    slots
+}
+
+fn is_trigger(words: &[&str]) -> bool {
+    let is = |i: usize, k: &str| words.get(i).is_some_and(|w| w.eq_ignore_ascii_case(k));
+    is(0, "CREATE") && (is(1, "TRIGGER") || is(2, "TRIGGER"))
+}
+
+fn is_word_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_'
+}
+
+fn word_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start;
+    while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+        i += 1;
+    }
+    i
+}
+
+/// Returns the index right after the string literal, quoted identifier or
+/// comment starting at `i`, if there is one.
+fn skip_literal(bytes: &[u8], i: usize) -> Option<usize> {
+    match bytes[i] {
+        b'\'' | b'"' | b'`' => Some(skip_quoted(bytes, i, bytes[i])),
+        b'[' => Some(skip_quoted(bytes, i, b']')),
+        b'-' if bytes.get(i + 1) == Some(&b'-') => {
+            let mut i = i;
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+            Some(i)
+        }
+        b'/' if bytes.get(i + 1) == Some(&b'*') => {
+            let mut i = i + 2;
+            while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                i += 1;
+            }
+            Some((i + 2).min(bytes.len()))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the index right after the closing quote, honoring doubled quotes.
+fn skip_quoted(bytes: &[u8], start: usize, close: u8) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if bytes[i] == close {
+            if close != b']' && bytes.get(i + 1) == Some(&close) {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    i
+}
+
+/// Statements changing rows, which may follow a `WITH` clause.
+const DML: [&str; 4] = ["INSERT", "UPDATE", "DELETE", "REPLACE"];
+
+/// Returns the statement kind of `words`: its first word, or the data
+/// changing keyword following a `WITH` clause.
+fn statement_kind<'a>(words: &[&'a str]) -> Option<&'a str> {
+    match words.first() {
+        Some(first) if first.eq_ignore_ascii_case("WITH") => words
+            .iter()
+            .find(|w| DML.iter().any(|k| w.eq_ignore_ascii_case(k)))
+            .or(Some(first))
+            .copied(),
+        first => first.copied(),
+    }
+}
+
+/// Best effort check that a statement doesn't write, used to guard
+/// connections marked as read-only.
+pub fn is_read_only(sql: &str) -> bool {
+    const WRITES: [&str; 11] = [
+        "INSERT", "UPDATE", "DELETE", "CREATE", "DROP", "ALTER", "ATTACH", "DETACH", "VACUUM",
+        "REINDEX", "ANALYZE",
+    ];
+
+    let words = words(sql);
+    let Some(first) = statement_kind(&words) else {
+        return true;
+    };
+    let allowed = ["SELECT", "WITH", "EXPLAIN", "VALUES", "PRAGMA"]
+        .iter()
+        .any(|k| first.eq_ignore_ascii_case(k));
+    if !allowed || (first.eq_ignore_ascii_case("PRAGMA") && sql.contains('=')) {
+        return false;
+    }
+
+    !words
+        .iter()
+        .any(|w| WRITES.iter().any(|k| w.eq_ignore_ascii_case(k)))
+}
+
+/// Best effort check that a statement returns rows, statements that don't
+/// are executed to report the rows they changed.
+pub fn returns_rows(sql: &str) -> bool {
+    const EXECUTE: [&str; 18] = [
+        "INSERT",
+        "UPDATE",
+        "DELETE",
+        "REPLACE",
+        "CREATE",
+        "DROP",
+        "ALTER",
+        "ATTACH",
+        "DETACH",
+        "VACUUM",
+        "REINDEX",
+        "ANALYZE",
+        "BEGIN",
+        "COMMIT",
+        "END",
+        "ROLLBACK",
+        "SAVEPOINT",
+        "RELEASE",
+    ];
+
+    let words = words(sql);
+    let Some(kind) = statement_kind(&words) else {
+        return true;
+    };
+    if words.iter().any(|w| w.eq_ignore_ascii_case("RETURNING")) {
+        return true;
+    }
+    !EXECUTE.iter().any(|k| kind.eq_ignore_ascii_case(k))
+}
+
+/// Best effort check that a statement inserts rows, giving it a last insert
+/// rowid.
+pub fn is_insert(sql: &str) -> bool {
+    statement_kind(&words(sql))
+        .is_some_and(|w| w.eq_ignore_ascii_case("INSERT") || w.eq_ignore_ascii_case("REPLACE"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words() {
+        let sql = "SELECT 'it''s -- not' AS \"a b\", x_1 -- trailing\n/* block */ FROM t";
+        assert_eq!(words(sql), vec!["SELECT", "AS", "x_1", "FROM", "t"]);
+    }
+
+    #[test]
+    fn test_is_read_only() {
+        assert!(is_read_only("select * from t"));
+        assert!(is_read_only("WITH x AS (SELECT 1) SELECT * FROM x"));
+        assert!(is_read_only("SELECT 'delete' FROM t"));
+        assert!(is_read_only("PRAGMA table_info(t)"));
+        assert!(!is_read_only("PRAGMA user_version = 3"));
+        assert!(!is_read_only("INSERT INTO t VALUES (1)"));
+        assert!(!is_read_only("WITH x AS (SELECT 1) DELETE FROM t"));
+        assert!(!is_read_only(
+            "WITH x AS (SELECT 1) REPLACE INTO t SELECT * FROM x"
+        ));
+        assert!(!is_read_only("-- comment\nDROP TABLE t"));
+    }
+
+    #[test]
+    fn test_returns_rows() {
+        assert!(returns_rows("SELECT * FROM t"));
+        assert!(returns_rows("PRAGMA table_info(t)"));
+        assert!(returns_rows("INSERT INTO t VALUES (1) RETURNING id"));
+        assert!(returns_rows("WITH x AS (SELECT 'delete') SELECT * FROM x"));
+        assert!(!returns_rows("insert into t values (1)"));
+        assert!(!returns_rows("WITH x AS (SELECT 1) DELETE FROM t"));
+        assert!(!returns_rows("-- setup\nCREATE TABLE t (a)"));
+        assert!(!returns_rows("BEGIN"));
+    }
+
+    #[test]
+    fn test_is_insert() {
+        assert!(is_insert("insert or replace into t values (1)"));
+        assert!(is_insert("REPLACE INTO t VALUES (1)"));
+        assert!(is_insert(
+            "WITH x AS (SELECT 1) INSERT INTO t SELECT * FROM x"
+        ));
+        assert!(!is_insert("WITH x AS (SELECT 1) DELETE FROM t"));
+        assert!(!is_insert("UPDATE t SET a = 1"));
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let named = |name: &str| Some(Placeholder::Named(name.to_string()));
+        let anonymous = Some(Placeholder::Anonymous);
+
+        assert_eq!(
+            placeholders("SELECT ?, ':x', :id, ? -- ?\n, @id, :id"),
+            vec![
+                anonymous.clone(),
+                named(":id"),
+                anonymous.clone(),
+                named("@id")
+            ]
+        );
+        assert_eq!(
+            placeholders("SELECT ?3, $name, ?, ?3"),
+            vec![None, None, named("?3"), named("$name"), anonymous]
+        );
+        assert!(placeholders("SELECT a FROM t").is_empty());
+    }
+
+    #[test]
+    fn test_statement_at() {
+        let sql = "  SELECT 1;\nSELECT ';' ;  \nSELECT 3";
+        let at = |offset| statement_at(sql, offset).map(|r| &sql[r]);
+
+        assert_eq!(at(0), Some("SELECT 1"));
+        assert_eq!(at(4), Some("SELECT 1"));
+        assert_eq!(at(11), Some("SELECT 1"));
+        assert_eq!(at(19), Some("SELECT ';'"));
+        assert_eq!(at(sql.len()), Some("SELECT 3"));
+        assert_eq!(statement_at("-- nothing", 0), None);
+    }
+
+    #[test]
+    fn test_statements() {
+        let split = |sql| {
+            statements(sql)
+                .into_iter()
+                .map(|r| &sql[r])
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(split("SELECT 1"), vec!["SELECT 1"]);
+        assert_eq!(
+            split("  SELECT ';' ;\n-- x;y\nSELECT 2;;  "),
+            vec!["SELECT ';'", "-- x;y\nSELECT 2"]
+        );
+        assert_eq!(split("SELECT 1; -- done"), vec!["SELECT 1"]);
+        assert_eq!(
+            split(
+                "CREATE TRIGGER tr AFTER INSERT ON t BEGIN \
+                 UPDATE t SET b = CASE WHEN a > 0 THEN 1 END; DELETE FROM u; END; SELECT 3"
+            ),
+            vec![
+                "CREATE TRIGGER tr AFTER INSERT ON t BEGIN \
+                 UPDATE t SET b = CASE WHEN a > 0 THEN 1 END; DELETE FROM u; END",
+                "SELECT 3"
+            ]
+        );
+    }
+}
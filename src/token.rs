@@ -0,0 +1,142 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Deserialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The claims of a Turso database token we care about.
+#[derive(Debug, Deserialize)]
+pub struct Claims {
+    pub exp: Option<u64>,
+    /// Access level, `ro` or `rw`. Tokens without it have full access.
+    #[serde(rename = "a")]
+    pub access: Option<String>,
+}
+
+impl Claims {
+    /// Decodes the payload of a JWT without verifying its signature, which is
+    /// left to the server. Returns `None` for anything that isn't a JWT.
+    pub fn decode(token: &str) -> Option<Self> {
+        let payload = token.split('.').nth(1)?;
+        let payload = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+        serde_json::from_slice(&payload).ok()
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.access.as_deref() == Some("ro")
+    }
+}
+
+/// Fails with an actionable message when the token of database `name` is
+/// already expired, so it doesn't surface as an opaque HTTP error on the first
+/// query.
+pub fn validate(name: &str, token: &str) -> anyhow::Result<()> {
+    match Claims::decode(token).and_then(|c| c.exp) {
+        Some(exp) => check_expiration(name, exp),
+        None => Ok(()),
+    }
+}
+
+/// Fails when `exp`, the expiration of the token of database `name`, has
+/// passed. Databases opened by URL are named after it, which the Turso CLI
+/// doesn't take.
+pub fn check_expiration(name: &str, exp: u64) -> anyhow::Result<()> {
+    if !is_expired(exp) {
+        return Ok(());
+    }
+    let command = if name.contains("://") {
+        "turso db tokens create".to_string()
+    } else {
+        format!("turso db tokens create {name}")
+    };
+    anyhow::bail!(
+        "Token for {name} expired at {}, run `{command}` to create a new one",
+        format_timestamp(exp)
+    )
+}
+
+/// Whether a unix timestamp expiration has passed, `0` meaning it never expires.
+fn is_expired(exp: u64) -> bool {
+    exp != 0 && exp <= now()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Formats a unix timestamp as `YYYY-MM-DD HH:MM:SS UTC`.
+fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // Civil date from days since the epoch, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jwt(payload: &str) -> String {
+        format!(
+            "{}.{}.signature",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"EdDSA","typ":"JWT"}"#),
+            URL_SAFE_NO_PAD.encode(payload)
+        )
+    }
+
+    #[test]
+    fn test_decode_claims() {
+        let claims = Claims::decode(&jwt(r#"{"a":"ro","exp":1700000000,"id":"x"}"#)).unwrap();
+        assert_eq!(claims.exp, Some(1700000000));
+        assert!(claims.is_read_only());
+
+        let claims = Claims::decode(&jwt(r#"{"id":"x"}"#)).unwrap();
+        assert_eq!(claims.exp, None);
+        assert!(!claims.is_read_only());
+
+        assert!(Claims::decode("not-a-jwt").is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        let err = validate("mydb", &jwt(r#"{"exp":1700000000}"#)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Token for mydb expired at 2023-11-14 22:13:20 UTC, run `turso db tokens create mydb` to create a new one"
+        );
+
+        assert!(validate("mydb", &jwt(r#"{"exp":4102444800}"#)).is_ok());
+        assert!(validate("mydb", &jwt(r#"{"id":"x"}"#)).is_ok());
+        assert!(validate("mydb", "").is_ok());
+        assert!(check_expiration("mydb", 0).is_ok());
+        let err = check_expiration("libsql://mydb.turso.io", 1700000000).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Token for libsql://mydb.turso.io expired at 2023-11-14 22:13:20 UTC, run `turso db tokens create` to create a new one"
+        );
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_timestamp(4_102_444_799), "2099-12-31 23:59:59 UTC");
+    }
+}